@@ -0,0 +1,91 @@
+use super::CartridgeError;
+
+/// Offset of the first byte past the header
+pub const HEADER_END: usize = 0x0150;
+
+/// Bitmap at `0x0104`–`0x0133` the boot ROM refuses to start without
+pub const LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+const LOGO_START: usize = 0x0104;
+const CHECKSUM: usize = 0x014D;
+/// Largest ROM size code, 8MB in 512 banks
+const MAX_ROM_SIZE_CODE: u8 = 0x08;
+
+/// Metadata stored at `0x0134`–`0x014F` of every cartridge ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    /// Selects the memory bank controller and extra hardware
+    pub cartridge_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+}
+
+impl CartridgeHeader {
+    /// Parse the header, checking the logo and header checksum the boot ROM
+    /// verifies and that the ROM size code is one of `0x00`–`0x08`
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::RomTooSmall(rom.len()));
+        }
+
+        if rom[LOGO_START..LOGO_START + LOGO.len()] != LOGO {
+            return Err(CartridgeError::BadLogo);
+        }
+
+        let expected = Self::checksum(rom);
+        if rom[CHECKSUM] != expected {
+            return Err(CartridgeError::HeaderChecksum {
+                expected,
+                actual: rom[CHECKSUM],
+            });
+        }
+
+        let rom_size_code = rom[0x0148];
+        if rom_size_code > MAX_ROM_SIZE_CODE {
+            return Err(CartridgeError::UnknownRomSize(rom_size_code));
+        }
+
+        let title = rom[0x0134..=0x0143]
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+
+        Ok(Self {
+            title,
+            cartridge_type: rom[0x0147],
+            rom_size_code,
+            ram_size_code: rom[0x0149],
+        })
+    }
+
+    /// Checksum over `0x0134`–`0x014C` as stored at `0x014D`
+    ///
+    /// `rom` must be at least [`HEADER_END`] bytes long.
+    pub fn checksum(rom: &[u8]) -> u8 {
+        rom[0x0134..CHECKSUM]
+            .iter()
+            .fold(0u8, |acc, &b| acc.wrapping_sub(b).wrapping_sub(1))
+    }
+
+    /// Number of 16KB ROM banks
+    pub fn rom_banks(&self) -> usize {
+        2 << self.rom_size_code
+    }
+
+    /// Size of external RAM in bytes
+    pub fn ram_size(&self) -> usize {
+        match self.ram_size_code {
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            _ => 0,
+        }
+    }
+}
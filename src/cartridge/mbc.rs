@@ -0,0 +1,542 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::CartridgeError;
+
+/// Size of a switchable ROM bank
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
+/// Size of a switchable external RAM bank
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// A memory bank controller, mapping CPU addresses onto cartridge ROM and RAM
+///
+/// The cartridge owns the bytes, a controller only holds banking state.
+pub trait Mbc {
+    /// Human readable controller name
+    fn name(&self) -> &'static str;
+    /// Read from `0x0000`–`0x7FFF`
+    fn read_rom(&self, rom: &[u8], addr: u16) -> u8;
+    /// Write to `0x0000`–`0x7FFF`, ROM is read-only so these set banking registers
+    fn write_rom(&mut self, addr: u16, value: u8);
+    /// Read from `0xA000`–`0xBFFF`
+    fn read_ram(&self, ram: &[u8], addr: u16) -> u8;
+    /// Write to `0xA000`–`0xBFFF`
+    fn write_ram(&mut self, ram: &mut [u8], addr: u16, value: u8);
+    /// Banking registers, for save states
+    fn save_state(&self) -> Vec<u8>;
+    /// Restore banking registers saved by [`Mbc::save_state`]
+    ///
+    /// A malformed state is rejected without changing any register.
+    fn load_state(&mut self, state: &[u8]) -> Result<(), CartridgeError>;
+}
+
+/// Select the controller for a header cartridge-type byte
+pub fn from_cartridge_type(cartridge_type: u8) -> Option<Box<dyn Mbc>> {
+    let mbc: Box<dyn Mbc> = match cartridge_type {
+        0x00 | 0x08 | 0x09 => Box::new(NoMbc),
+        0x01..=0x03 => Box::new(Mbc1::new()),
+        0x0F..=0x13 => Box::new(Mbc3::new()),
+        0x19..=0x1E => Box::new(Mbc5::new()),
+        _ => return None,
+    };
+
+    Some(mbc)
+}
+
+/// Index of `offset` within `bank`, wrapping the bank number to the number
+/// of banks present in `len` bytes
+fn banked_index(len: usize, bank_size: usize, bank: usize, offset: usize) -> Option<usize> {
+    let banks = len / bank_size;
+    if banks == 0 {
+        return None;
+    }
+
+    Some((bank % banks) * bank_size + offset)
+}
+
+/// ROM-only carts, 32KB of ROM mapped directly with optional unbanked RAM
+#[derive(Debug, Default)]
+pub struct NoMbc;
+
+impl Mbc for NoMbc {
+    fn name(&self) -> &'static str {
+        "ROM only"
+    }
+
+    fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        rom.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _addr: u16, _value: u8) {}
+
+    fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        ram.get((addr - 0xA000) as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], addr: u16, value: u8) {
+        if let Some(byte) = ram.get_mut((addr - 0xA000) as usize) {
+            *byte = value;
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), CartridgeError> {
+        match state {
+            [] => Ok(()),
+            _ => Err(CartridgeError::InvalidMbcState(self.name())),
+        }
+    }
+}
+
+/// MBC1, up to 2MB of ROM and 32KB of RAM
+#[derive(Debug, Default)]
+pub struct Mbc1 {
+    ram_enabled: bool,
+    /// Lower five bits of the ROM bank
+    rom_bank: u8,
+    /// Two bits selecting a RAM bank or the upper ROM bank bits
+    upper_bank: u8,
+    /// When set, `upper_bank` also applies to `0x0000`–`0x3FFF` and RAM
+    advanced_mode: bool,
+}
+
+impl Mbc1 {
+    pub fn new() -> Self {
+        Self {
+            rom_bank: 1,
+            ..Default::default()
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_mode {
+            self.upper_bank as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Mbc for Mbc1 {
+    fn name(&self) -> &'static str {
+        "MBC1"
+    }
+
+    fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        let bank = match addr {
+            0x0000..=0x3FFF if self.advanced_mode => (self.upper_bank as usize) << 5,
+            0x0000..=0x3FFF => 0,
+            _ => ((self.upper_bank as usize) << 5) | self.rom_bank as usize,
+        };
+
+        banked_index(
+            rom.len(),
+            ROM_BANK_SIZE,
+            bank,
+            addr as usize % ROM_BANK_SIZE,
+        )
+        .map_or(0xFF, |idx| rom[idx])
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x1F).max(1),
+            0x4000..=0x5FFF => self.upper_bank = value & 0b11,
+            _ => self.advanced_mode = value & 1 != 0,
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        banked_index(
+            ram.len(),
+            RAM_BANK_SIZE,
+            self.ram_bank(),
+            (addr - 0xA000) as usize,
+        )
+        .map_or(0xFF, |idx| ram[idx])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        if let Some(idx) = banked_index(
+            ram.len(),
+            RAM_BANK_SIZE,
+            self.ram_bank(),
+            (addr - 0xA000) as usize,
+        ) {
+            ram[idx] = value;
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        vec![
+            self.ram_enabled as u8,
+            self.rom_bank,
+            self.upper_bank,
+            self.advanced_mode as u8,
+        ]
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), CartridgeError> {
+        let &[ram_enabled, rom_bank, upper_bank, advanced_mode] = state else {
+            return Err(CartridgeError::InvalidMbcState(self.name()));
+        };
+
+        self.ram_enabled = ram_enabled != 0;
+        self.rom_bank = rom_bank;
+        self.upper_bank = upper_bank;
+        self.advanced_mode = advanced_mode != 0;
+        Ok(())
+    }
+}
+
+/// Seconds in a day
+const DAY: u64 = 86_400;
+
+/// Seconds since the Unix epoch, the default MBC3 clock source
+fn system_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The MBC3 real-time clock
+///
+/// Rather than being ticked, the counter is the number of seconds the clock
+/// source has advanced since `epoch`, and the registers are derived from it
+/// when latched.
+#[derive(Debug)]
+struct Rtc {
+    clock: fn() -> u64,
+    /// Clock reading at which the counter was zero
+    epoch: u64,
+    /// Counter value, frozen while the halt bit is set
+    halted: Option<u64>,
+    /// Seconds, minutes, hours, day low and day high as last latched
+    latched: [u8; 5],
+    /// The last write to the latch register was `0x00`
+    latch_armed: bool,
+}
+
+impl Rtc {
+    fn new(clock: fn() -> u64) -> Self {
+        Self {
+            clock,
+            epoch: clock(),
+            halted: None,
+            latched: [0; 5],
+            latch_armed: false,
+        }
+    }
+
+    fn seconds(&self) -> u64 {
+        self.halted
+            .unwrap_or_else(|| (self.clock)().wrapping_sub(self.epoch))
+    }
+
+    fn set_seconds(&mut self, seconds: u64) {
+        match &mut self.halted {
+            Some(halted) => *halted = seconds,
+            None => self.epoch = (self.clock)().wrapping_sub(seconds),
+        }
+    }
+
+    /// Current register values, days past 511 set the day carry bit
+    fn registers(&self) -> [u8; 5] {
+        let seconds = self.seconds();
+        let days = seconds / DAY;
+
+        let day_high = (days >> 8) as u8 & 0x01
+            | (self.halted.is_some() as u8) << 6
+            | ((days > 0x1FF) as u8) << 7;
+
+        [
+            (seconds % 60) as u8,
+            (seconds / 60 % 60) as u8,
+            (seconds / 3600 % 24) as u8,
+            days as u8,
+            day_high,
+        ]
+    }
+
+    /// Write the register selected by `0x08`–`0x0C`
+    fn write(&mut self, select: u8, value: u8) {
+        let mut registers = self.registers();
+        registers[(select - 0x08) as usize] = value;
+        let [seconds, minutes, hours, day_low, day_high] = registers;
+
+        let days = day_low as u64 | (day_high as u64 & 0x01) << 8 | (day_high as u64 & 0x80) << 2;
+        let counter = days * DAY
+            + (hours as u64 % 24) * 3600
+            + (minutes as u64 % 60) * 60
+            + seconds as u64 % 60;
+
+        self.halted = None;
+        self.set_seconds(counter);
+        if day_high & 0x40 != 0 {
+            self.halted = Some(counter);
+        }
+    }
+
+    /// Writing `0x00` then `0x01` copies the counter into the readable
+    /// registers
+    fn latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers();
+        }
+        self.latch_armed = value == 0x00;
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.epoch.to_le_bytes());
+        out.push(self.halted.is_some() as u8);
+        out.extend_from_slice(&self.halted.unwrap_or(0).to_le_bytes());
+        out.extend_from_slice(&self.latched);
+        out.push(self.latch_armed as u8);
+    }
+
+    /// Restore from the 23 bytes written by [`Rtc::save_state`]
+    fn load_state(&mut self, state: &[u8; 23]) {
+        let (epoch, rest) = state.split_at(8);
+        let (halted, rest) = rest.split_at(9);
+        let (latched, armed) = rest.split_at(5);
+
+        self.epoch = u64::from_le_bytes(epoch.try_into().expect("8 bytes"));
+        self.halted =
+            (halted[0] != 0).then(|| u64::from_le_bytes(halted[1..].try_into().expect("8 bytes")));
+        self.latched.copy_from_slice(latched);
+        self.latch_armed = armed[0] != 0;
+    }
+}
+
+/// MBC3, up to 2MB of ROM, 32KB of RAM and a real-time clock
+#[derive(Debug)]
+pub struct Mbc3 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    /// `0x00`–`0x03` select a RAM bank, `0x08`–`0x0C` an RTC register
+    ram_select: u8,
+    rtc: Rtc,
+}
+
+impl Default for Mbc3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mbc3 {
+    pub fn new() -> Self {
+        Self::with_clock(system_clock)
+    }
+
+    /// Drive the RTC from `clock`, which returns a count of seconds
+    pub fn with_clock(clock: fn() -> u64) -> Self {
+        Self {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+            rtc: Rtc::new(clock),
+        }
+    }
+
+    /// Set the RTC counter to `seconds` as of now
+    pub fn set_rtc_seconds(&mut self, seconds: u64) {
+        self.rtc.set_seconds(seconds);
+    }
+}
+
+impl Mbc for Mbc3 {
+    fn name(&self) -> &'static str {
+        "MBC3"
+    }
+
+    fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+
+        banked_index(
+            rom.len(),
+            ROM_BANK_SIZE,
+            bank,
+            addr as usize % ROM_BANK_SIZE,
+        )
+        .map_or(0xFF, |idx| rom[idx])
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_select = value,
+            _ => self.rtc.latch(value),
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match self.ram_select {
+            0x00..=0x03 => banked_index(
+                ram.len(),
+                RAM_BANK_SIZE,
+                self.ram_select as usize,
+                (addr - 0xA000) as usize,
+            )
+            .map_or(0xFF, |idx| ram[idx]),
+            0x08..=0x0C => self.rtc.latched[(self.ram_select - 0x08) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match self.ram_select {
+            0x00..=0x03 => {
+                if let Some(idx) = banked_index(
+                    ram.len(),
+                    RAM_BANK_SIZE,
+                    self.ram_select as usize,
+                    (addr - 0xA000) as usize,
+                ) {
+                    ram[idx] = value;
+                }
+            }
+            0x08..=0x0C => self.rtc.write(self.ram_select, value),
+            _ => {}
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = vec![self.ram_enabled as u8, self.rom_bank, self.ram_select];
+        self.rtc.save_state(&mut state);
+        state
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), CartridgeError> {
+        let [ram_enabled, rom_bank, ram_select, rtc @ ..] = state else {
+            return Err(CartridgeError::InvalidMbcState(self.name()));
+        };
+        let Ok(rtc) = rtc.try_into() else {
+            return Err(CartridgeError::InvalidMbcState(self.name()));
+        };
+
+        self.ram_enabled = *ram_enabled != 0;
+        self.rom_bank = *rom_bank;
+        self.ram_select = *ram_select;
+        self.rtc.load_state(rtc);
+        Ok(())
+    }
+}
+
+/// MBC5, up to 8MB of ROM and 128KB of RAM
+#[derive(Debug, Default)]
+pub struct Mbc5 {
+    ram_enabled: bool,
+    /// Nine bit ROM bank, unlike MBC1/3 bank 0 can be mapped high
+    rom_bank: u16,
+    ram_bank: u8,
+}
+
+impl Mbc5 {
+    pub fn new() -> Self {
+        Self {
+            rom_bank: 1,
+            ..Default::default()
+        }
+    }
+}
+
+impl Mbc for Mbc5 {
+    fn name(&self) -> &'static str {
+        "MBC5"
+    }
+
+    fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+
+        banked_index(
+            rom.len(),
+            ROM_BANK_SIZE,
+            bank,
+            addr as usize % ROM_BANK_SIZE,
+        )
+        .map_or(0xFF, |idx| rom[idx])
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 1) << 8),
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        banked_index(
+            ram.len(),
+            RAM_BANK_SIZE,
+            self.ram_bank as usize,
+            (addr - 0xA000) as usize,
+        )
+        .map_or(0xFF, |idx| ram[idx])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        if let Some(idx) = banked_index(
+            ram.len(),
+            RAM_BANK_SIZE,
+            self.ram_bank as usize,
+            (addr - 0xA000) as usize,
+        ) {
+            ram[idx] = value;
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let [lo, hi] = self.rom_bank.to_le_bytes();
+        vec![self.ram_enabled as u8, lo, hi, self.ram_bank]
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), CartridgeError> {
+        let &[ram_enabled, lo, hi, ram_bank] = state else {
+            return Err(CartridgeError::InvalidMbcState(self.name()));
+        };
+
+        self.ram_enabled = ram_enabled != 0;
+        self.rom_bank = u16::from_le_bytes([lo, hi]);
+        self.ram_bank = ram_bank;
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+pub mod header;
+pub mod mbc;
+
+use thiserror::Error;
+
+use crate::memory::Address;
+
+use header::CartridgeHeader;
+use mbc::Mbc;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CartridgeError {
+    #[error("ROM of {0} bytes is too small to contain a cartridge header")]
+    RomTooSmall(usize),
+    #[error("cartridge header is missing the boot logo")]
+    BadLogo,
+    #[error("header checksum is {actual:#04X}, expected {expected:#04X}")]
+    HeaderChecksum { expected: u8, actual: u8 },
+    #[error("unknown ROM size code {0:#04X}")]
+    UnknownRomSize(u8),
+    #[error("unsupported cartridge type {0:#04X}")]
+    UnsupportedCartridgeType(u8),
+    #[error("malformed {0} banking state")]
+    InvalidMbcState(&'static str),
+    #[error("expected {expected} bytes of cartridge RAM, got {actual}")]
+    RamSizeMismatch { expected: usize, actual: usize },
+}
+
+/// A game cartridge, its ROM, external RAM and bank controller
+pub struct Cartridge {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: Box<dyn Mbc>,
+}
+
+impl Cartridge {
+    pub fn new(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        let header = CartridgeHeader::parse(&rom)?;
+
+        let mbc = mbc::from_cartridge_type(header.cartridge_type).ok_or(
+            CartridgeError::UnsupportedCartridgeType(header.cartridge_type),
+        )?;
+
+        Ok(Self {
+            ram: vec![0; header.ram_size()],
+            header,
+            rom,
+            mbc,
+        })
+    }
+
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    pub fn mbc(&self) -> &dyn Mbc {
+        self.mbc.as_ref()
+    }
+
+    pub fn mbc_mut(&mut self) -> &mut dyn Mbc {
+        self.mbc.as_mut()
+    }
+
+    /// External RAM, across all banks
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Replace external RAM, which must match the size the header declares
+    pub fn load_ram(&mut self, ram: &[u8]) -> Result<(), CartridgeError> {
+        if ram.len() != self.ram.len() {
+            return Err(CartridgeError::RamSizeMismatch {
+                expected: self.ram.len(),
+                actual: ram.len(),
+            });
+        }
+
+        self.ram.copy_from_slice(ram);
+        Ok(())
+    }
+
+    /// Read from the ROM (`0x0000`–`0x7FFF`) or external RAM
+    /// (`0xA000`–`0xBFFF`) regions
+    pub fn read(&self, addr: Address) -> u8 {
+        match addr.0 {
+            0x0000..=0x7FFF => self.mbc.read_rom(&self.rom, addr.0),
+            0xA000..=0xBFFF => self.mbc.read_ram(&self.ram, addr.0),
+            _ => 0xFF,
+        }
+    }
+
+    /// Write to the ROM (`0x0000`–`0x7FFF`) or external RAM
+    /// (`0xA000`–`0xBFFF`) regions
+    pub fn write(&mut self, addr: Address, value: u8) {
+        match addr.0 {
+            0x0000..=0x7FFF => self.mbc.write_rom(addr.0, value),
+            0xA000..=0xBFFF => self.mbc.write_ram(&mut self.ram, addr.0, value),
+            _ => {}
+        }
+    }
+}
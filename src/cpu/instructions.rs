@@ -0,0 +1,1029 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use thiserror::Error;
+
+use super::registers::{Flag, Register16, Register8, Registers};
+
+/// Prefix byte selecting the second opcode page
+pub const CB_PREFIX: u8 = 0xCB;
+
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    /// No instruction is decoded for `opcode`, on the CB page if `cb` is set
+    #[error(
+        "failed to match {}opcode {opcode:#04X} (x={}, y={}, z={})",
+        if *cb { "CB-prefixed " } else { "" },
+        opcode >> 6,
+        (opcode >> 3) & 0b111,
+        opcode & 0b111
+    )]
+    UnknownOpcode { opcode: u8, cb: bool },
+    #[error("cannot load {src} into {dst}")]
+    InvalidLoadOperands { dst: Operand, src: Operand },
+}
+
+/// Where an instruction reads its input from or writes its output to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register8(Register8),
+    Register16(Register16),
+    /// The byte in memory addressed by a 16-bit register, e.g. `(HL)`
+    Indirect(Register16),
+    /// The byte following the opcode
+    Immediate8,
+    /// The little-endian word following the opcode
+    Immediate16,
+    /// The byte in memory addressed by the word following the opcode, `(a16)`
+    IndirectImmediate16,
+    /// The byte in the `0xFF00` page at the offset following the opcode,
+    /// `(a8)`
+    HighImmediate8,
+    /// The byte in the `0xFF00` page at offset C, `(C)`
+    HighC,
+}
+
+/// Operands selected by 3-bit `r` fields, index 6 is `(HL)`
+pub const R_TABLE: [Operand; 8] = [
+    Operand::Register8(Register8::B),
+    Operand::Register8(Register8::C),
+    Operand::Register8(Register8::D),
+    Operand::Register8(Register8::E),
+    Operand::Register8(Register8::H),
+    Operand::Register8(Register8::L),
+    Operand::Indirect(Register16::HL),
+    Operand::Register8(Register8::A),
+];
+
+/// Register pairs selected by 2-bit `p` fields in most instructions
+pub const RP_TABLE: [Register16; 4] = [
+    Register16::BC,
+    Register16::DE,
+    Register16::HL,
+    Register16::SP,
+];
+
+/// Register pairs selected by 2-bit `p` fields in PUSH/POP
+pub const RP2_TABLE: [Register16; 4] = [
+    Register16::BC,
+    Register16::DE,
+    Register16::HL,
+    Register16::AF,
+];
+
+impl Operand {
+    pub fn from_r_table(idx: u8) -> Self {
+        R_TABLE[idx as usize & 0b111]
+    }
+
+    pub fn from_rp_table(idx: u8) -> Self {
+        Operand::Register16(RP_TABLE[idx as usize & 0b11])
+    }
+
+    pub fn from_rp2_table(idx: u8) -> Self {
+        Operand::Register16(RP2_TABLE[idx as usize & 0b11])
+    }
+
+    /// Whether this operand names a location in memory
+    pub fn is_indirect(&self) -> bool {
+        matches!(
+            self,
+            Operand::Indirect(_)
+                | Operand::IndirectImmediate16
+                | Operand::HighImmediate8
+                | Operand::HighC
+        )
+    }
+
+    /// Bytes this operand occupies after the opcode
+    pub fn immediate_len(&self) -> u8 {
+        match self {
+            Operand::Immediate8 | Operand::HighImmediate8 => 1,
+            Operand::Immediate16 | Operand::IndirectImmediate16 => 2,
+            _ => 0,
+        }
+    }
+
+    /// Machine cycles spent on memory accesses to use this operand
+    pub fn memory_cycles(&self) -> u8 {
+        match self {
+            Operand::Register8(_) | Operand::Register16(_) => 0,
+            Operand::Indirect(_) | Operand::Immediate8 | Operand::HighC => 1,
+            Operand::Immediate16 | Operand::HighImmediate8 => 2,
+            Operand::IndirectImmediate16 => 3,
+        }
+    }
+}
+
+/// The eight accumulator operations, in `y` field order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+}
+
+impl ArithOp {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            ArithOp::Add => "ADD",
+            ArithOp::Adc => "ADC",
+            ArithOp::Sub => "SUB",
+            ArithOp::Sbc => "SBC",
+            ArithOp::And => "AND",
+            ArithOp::Xor => "XOR",
+            ArithOp::Or => "OR",
+            ArithOp::Cp => "CP",
+        }
+    }
+}
+
+impl TryFrom<u8> for ArithOp {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        let op = match value {
+            0 => ArithOp::Add,
+            1 => ArithOp::Adc,
+            2 => ArithOp::Sub,
+            3 => ArithOp::Sbc,
+            4 => ArithOp::And,
+            5 => ArithOp::Xor,
+            6 => ArithOp::Or,
+            7 => ArithOp::Cp,
+            _ => bail!("no ALU operation with index {value}"),
+        };
+
+        Ok(op)
+    }
+}
+
+/// Adjustment made to HL after an `(HL+)`/`(HL-)` load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowUp {
+    Inc,
+    Dec,
+}
+
+/// Flag tests selecting whether a conditional branch is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    NZ,
+    Z,
+    NC,
+    C,
+}
+
+impl Condition {
+    /// Whether the flags in `registers` satisfy the condition
+    pub fn is_met(&self, registers: &Registers) -> bool {
+        match self {
+            Condition::NZ => !registers.flag(Flag::Zero),
+            Condition::Z => registers.flag(Flag::Zero),
+            Condition::NC => !registers.flag(Flag::Carry),
+            Condition::C => registers.flag(Flag::Carry),
+        }
+    }
+}
+
+impl TryFrom<u8> for Condition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        let condition = match value {
+            0 => Condition::NZ,
+            1 => Condition::Z,
+            2 => Condition::NC,
+            3 => Condition::C,
+            _ => bail!("no condition with index {value}"),
+        };
+
+        Ok(condition)
+    }
+}
+
+/// Rotations on the CB page, in `y` field order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotateOp {
+    /// Rotate left, bit 7 goes to both carry and bit 0
+    Rlc,
+    /// Rotate right, bit 0 goes to both carry and bit 7
+    Rrc,
+    /// Rotate left through carry
+    Rl,
+    /// Rotate right through carry
+    Rr,
+}
+
+impl RotateOp {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            RotateOp::Rlc => "RLC",
+            RotateOp::Rrc => "RRC",
+            RotateOp::Rl => "RL",
+            RotateOp::Rr => "RR",
+        }
+    }
+}
+
+impl TryFrom<u8> for RotateOp {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        let op = match value {
+            0 => RotateOp::Rlc,
+            1 => RotateOp::Rrc,
+            2 => RotateOp::Rl,
+            3 => RotateOp::Rr,
+            _ => bail!("no rotate operation with index {value}"),
+        };
+
+        Ok(op)
+    }
+}
+
+/// Shifts on the CB page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftOp {
+    /// Shift left, bit 0 becomes 0
+    Sla,
+    /// Shift right, bit 7 is kept
+    Sra,
+    /// Shift right, bit 7 becomes 0
+    Srl,
+}
+
+impl ShiftOp {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            ShiftOp::Sla => "SLA",
+            ShiftOp::Sra => "SRA",
+            ShiftOp::Srl => "SRL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionType {
+    Nop,
+    Halt,
+    /// Two bytes, `0x10 0x00`, though the second is ignored
+    Stop,
+    DisableInterrupts,
+    EnableInterrupts,
+    Load {
+        dst: Operand,
+        src: Operand,
+        followup: Option<FollowUp>,
+    },
+    /// `LD HL,SP+e`, flags are set as for [`InstructionType::AddSp`]
+    LoadHlSp,
+    /// `ADD SP,e`, H and C come from adding the low byte of SP and the
+    /// displacement as unsigned bytes, Z and N are cleared
+    AddSp,
+    /// `LD (a16),SP`, store SP little-endian at the word following the
+    /// opcode
+    StoreSp,
+    Push(Register16),
+    Pop(Register16),
+    /// Add the signed byte following the opcode to PC, if `condition` holds
+    JumpRelative {
+        condition: Option<Condition>,
+    },
+    /// Load the word following the opcode into PC, if `condition` holds
+    Jump {
+        condition: Option<Condition>,
+    },
+    /// `JP (HL)`, copies HL into PC without reading memory
+    JumpHl,
+    /// Push the address of the next instruction and jump to the word
+    /// following the opcode, if `condition` holds
+    Call {
+        condition: Option<Condition>,
+    },
+    /// Push PC and jump to a fixed vector in the first page
+    Rst(u16),
+    /// Pop PC, if `condition` holds. RETI also sets IME immediately.
+    Return {
+        condition: Option<Condition>,
+        reti: bool,
+    },
+    /// Increment an 8-bit operand, setting flags, or a register pair,
+    /// leaving them alone
+    Inc(Operand),
+    /// Decrement, with the same flag behaviour as [`InstructionType::Inc`]
+    Dec(Operand),
+    /// Unprefixed rotate of A, unlike [`InstructionType::Rotate`] Z is
+    /// always cleared
+    RotateA(RotateOp),
+    /// Correct A to packed BCD after an addition or subtraction
+    Daa,
+    /// Complement A
+    Cpl,
+    /// Set the carry flag
+    Scf,
+    /// Complement the carry flag
+    Ccf,
+    /// `ADD HL,rr`, Z is left alone
+    Arith16(Register16),
+    /// `A <- A op src`, CP only sets flags
+    Arith8 {
+        op: ArithOp,
+        src: Operand,
+    },
+    /// CB-prefixed rotate of `operand` in place
+    Rotate {
+        op: RotateOp,
+        operand: Operand,
+    },
+    /// CB-prefixed shift of `operand` in place, the bit shifted out goes to
+    /// carry
+    Shift {
+        op: ShiftOp,
+        operand: Operand,
+    },
+    /// CB-prefixed exchange of the high and low nibbles of the operand
+    Swap(Operand),
+    /// CB-prefixed test of bit `bit` of the operand, only sets flags
+    Bit {
+        bit: u8,
+        operand: Operand,
+    },
+    /// CB-prefixed RES, clear bit `bit` of the operand
+    ResBit {
+        bit: u8,
+        operand: Operand,
+    },
+    /// CB-prefixed SET, set bit `bit` of the operand
+    SetBit {
+        bit: u8,
+        operand: Operand,
+    },
+}
+
+/// A decoded instruction and its cost in machine cycles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    itype: InstructionType,
+    cycles: u8,
+    /// Cost when a conditional branch is not taken
+    cycles_not_taken: u8,
+}
+
+impl Instruction {
+    fn new(itype: InstructionType, cycles: u8) -> Self {
+        Self::branch(itype, cycles, cycles)
+    }
+
+    fn branch(itype: InstructionType, cycles: u8, cycles_not_taken: u8) -> Self {
+        Self {
+            itype,
+            cycles,
+            cycles_not_taken,
+        }
+    }
+
+    pub fn nop() -> Self {
+        Self::new(InstructionType::Nop, 1)
+    }
+
+    pub fn halt() -> Self {
+        Self::new(InstructionType::Halt, 1)
+    }
+
+    pub fn stop() -> Self {
+        Self::new(InstructionType::Stop, 1)
+    }
+
+    pub fn di() -> Self {
+        Self::new(InstructionType::DisableInterrupts, 1)
+    }
+
+    pub fn ei() -> Self {
+        Self::new(InstructionType::EnableInterrupts, 1)
+    }
+
+    /// Like [`Instruction::load`], but rejecting operand pairs no load could
+    /// execute, such as an immediate destination or two memory operands
+    pub fn try_load(dst: Operand, src: Operand) -> Result<Self, DecodeError> {
+        if !Self::valid_load(dst, src) {
+            return Err(DecodeError::InvalidLoadOperands { dst, src });
+        }
+
+        Ok(Self::load(dst, src))
+    }
+
+    fn valid_load(dst: Operand, src: Operand) -> bool {
+        match (dst, src) {
+            (Operand::Register16(_), src) => {
+                matches!(src, Operand::Register16(_) | Operand::Immediate16)
+            }
+            (Operand::Immediate8 | Operand::Immediate16, _) => false,
+            (_, Operand::Register16(_) | Operand::Immediate16) => false,
+            (dst, src) => !(dst.is_indirect() && src.is_indirect()),
+        }
+    }
+
+    /// A load between two operands the decoder knows to be compatible, see
+    /// [`Instruction::try_load`]
+    pub fn load(dst: Operand, src: Operand) -> Self {
+        debug_assert!(Self::valid_load(dst, src), "LD {dst},{src}");
+
+        // Moving a whole register pair takes an internal cycle
+        let internal = matches!((dst, src), (Operand::Register16(_), Operand::Register16(_)));
+        let cycles = 1 + dst.memory_cycles() + src.memory_cycles() + internal as u8;
+        let itype = InstructionType::Load {
+            dst,
+            src,
+            followup: None,
+        };
+        Self::new(itype, cycles)
+    }
+
+    /// A load through `(HL)` that then steps HL
+    pub fn load_hl(dst: Operand, src: Operand, followup: FollowUp) -> Self {
+        let itype = InstructionType::Load {
+            dst,
+            src,
+            followup: Some(followup),
+        };
+        Self::new(itype, 2)
+    }
+
+    pub fn load_hl_sp() -> Self {
+        Self::new(InstructionType::LoadHlSp, 3)
+    }
+
+    pub fn add_sp() -> Self {
+        Self::new(InstructionType::AddSp, 4)
+    }
+
+    pub fn store_sp() -> Self {
+        Self::new(InstructionType::StoreSp, 5)
+    }
+
+    pub fn push(reg: Register16) -> Self {
+        Self::new(InstructionType::Push(reg), 4)
+    }
+
+    pub fn pop(reg: Register16) -> Self {
+        Self::new(InstructionType::Pop(reg), 3)
+    }
+
+    pub fn jump_relative(condition: Option<Condition>) -> Self {
+        let not_taken = if condition.is_some() { 2 } else { 3 };
+        Self::branch(InstructionType::JumpRelative { condition }, 3, not_taken)
+    }
+
+    pub fn jump(condition: Option<Condition>) -> Self {
+        let not_taken = if condition.is_some() { 3 } else { 4 };
+        Self::branch(InstructionType::Jump { condition }, 4, not_taken)
+    }
+
+    pub fn jump_hl() -> Self {
+        Self::new(InstructionType::JumpHl, 1)
+    }
+
+    pub fn call(condition: Option<Condition>) -> Self {
+        let not_taken = if condition.is_some() { 3 } else { 6 };
+        Self::branch(InstructionType::Call { condition }, 6, not_taken)
+    }
+
+    pub fn ret(condition: Option<Condition>) -> Self {
+        let itype = InstructionType::Return {
+            condition,
+            reti: false,
+        };
+
+        // Checking the condition costs a cycle of its own
+        match condition {
+            Some(_) => Self::branch(itype, 5, 2),
+            None => Self::new(itype, 4),
+        }
+    }
+
+    pub fn reti() -> Self {
+        let itype = InstructionType::Return {
+            condition: None,
+            reti: true,
+        };
+        Self::new(itype, 4)
+    }
+
+    pub fn rst(vector: u16) -> Self {
+        Self::new(InstructionType::Rst(vector), 4)
+    }
+
+    pub fn inc(operand: Operand) -> Self {
+        Self::new(InstructionType::Inc(operand), Self::inc_dec_cycles(operand))
+    }
+
+    pub fn dec(operand: Operand) -> Self {
+        Self::new(InstructionType::Dec(operand), Self::inc_dec_cycles(operand))
+    }
+
+    /// Register pairs take an internal cycle, `(HL)` is read and written back
+    fn inc_dec_cycles(operand: Operand) -> u8 {
+        match operand {
+            Operand::Register16(_) => 2,
+            _ => 1 + 2 * operand.memory_cycles(),
+        }
+    }
+
+    pub fn rotate_a(op: RotateOp) -> Self {
+        Self::new(InstructionType::RotateA(op), 1)
+    }
+
+    pub fn daa() -> Self {
+        Self::new(InstructionType::Daa, 1)
+    }
+
+    pub fn cpl() -> Self {
+        Self::new(InstructionType::Cpl, 1)
+    }
+
+    pub fn scf() -> Self {
+        Self::new(InstructionType::Scf, 1)
+    }
+
+    pub fn ccf() -> Self {
+        Self::new(InstructionType::Ccf, 1)
+    }
+
+    pub fn arith16(src: Register16) -> Self {
+        Self::new(InstructionType::Arith16(src), 2)
+    }
+
+    pub fn arith8(op: ArithOp, src: Operand) -> Self {
+        Self::new(InstructionType::Arith8 { op, src }, 1 + src.memory_cycles())
+    }
+
+    /// CB-prefixed, `(HL)` is read and written back
+    pub fn rotate(op: RotateOp, operand: Operand) -> Self {
+        let cycles = 2 + 2 * operand.memory_cycles();
+        Self::new(InstructionType::Rotate { op, operand }, cycles)
+    }
+
+    pub fn shift(op: ShiftOp, operand: Operand) -> Self {
+        let cycles = 2 + 2 * operand.memory_cycles();
+        Self::new(InstructionType::Shift { op, operand }, cycles)
+    }
+
+    pub fn swap(operand: Operand) -> Self {
+        Self::new(
+            InstructionType::Swap(operand),
+            2 + 2 * operand.memory_cycles(),
+        )
+    }
+
+    /// CB-prefixed, `(HL)` is only read
+    pub fn bit(bit: u8, operand: Operand) -> Self {
+        Self::new(
+            InstructionType::Bit { bit, operand },
+            2 + operand.memory_cycles(),
+        )
+    }
+
+    pub fn res_bit(bit: u8, operand: Operand) -> Self {
+        let cycles = 2 + 2 * operand.memory_cycles();
+        Self::new(InstructionType::ResBit { bit, operand }, cycles)
+    }
+
+    pub fn set_bit(bit: u8, operand: Operand) -> Self {
+        let cycles = 2 + 2 * operand.memory_cycles();
+        Self::new(InstructionType::SetBit { bit, operand }, cycles)
+    }
+
+    pub fn itype(&self) -> &InstructionType {
+        &self.itype
+    }
+
+    /// Display with the immediates filled in from `operands`, the bytes
+    /// following the opcode, e.g. `JR NZ,-2` rather than `JR NZ,r8`
+    pub fn with_operands<'a>(&'a self, operands: &'a [u8]) -> impl fmt::Display + 'a {
+        struct WithOperands<'a>(&'a InstructionType, &'a [u8]);
+
+        impl fmt::Display for WithOperands<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_with(f, self.1)
+            }
+        }
+
+        WithOperands(&self.itype, operands)
+    }
+
+    /// Cost in machine cycles, for a conditional branch this is when it's
+    /// taken
+    pub fn cycles(&self) -> u8 {
+        self.cycles
+    }
+
+    /// Cost in machine cycles when a conditional branch is not taken, the
+    /// same as [`Self::cycles`] for everything else
+    pub fn cycles_not_taken(&self) -> u8 {
+        self.cycles_not_taken
+    }
+
+    /// Size in bytes, including the opcode
+    pub fn length(&self) -> u8 {
+        let operands = match self.itype {
+            InstructionType::Load { dst, src, .. } => dst.immediate_len() + src.immediate_len(),
+            InstructionType::Arith8 { src, .. } => src.immediate_len(),
+            InstructionType::Jump { .. }
+            | InstructionType::Call { .. }
+            | InstructionType::StoreSp => 2,
+            InstructionType::JumpRelative { .. }
+            | InstructionType::Stop
+            | InstructionType::LoadHlSp
+            | InstructionType::AddSp
+            | InstructionType::Rotate { .. }
+            | InstructionType::Shift { .. }
+            | InstructionType::Swap(_)
+            | InstructionType::Bit { .. }
+            | InstructionType::ResBit { .. }
+            | InstructionType::SetBit { .. } => 1,
+            _ => 0,
+        };
+
+        1 + operands
+    }
+
+    /// Whether executing this reads or writes memory beyond fetching the
+    /// instruction itself
+    pub fn accesses_memory(&self) -> bool {
+        match self.itype {
+            InstructionType::Load { dst, src, .. } => dst.is_indirect() || src.is_indirect(),
+            InstructionType::Arith8 { src, .. } => src.is_indirect(),
+            InstructionType::Inc(operand) | InstructionType::Dec(operand) => operand.is_indirect(),
+            InstructionType::Rotate { operand, .. }
+            | InstructionType::Shift { operand, .. }
+            | InstructionType::Swap(operand)
+            | InstructionType::Bit { operand, .. }
+            | InstructionType::ResBit { operand, .. }
+            | InstructionType::SetBit { operand, .. } => operand.is_indirect(),
+            InstructionType::StoreSp
+            | InstructionType::Push(_)
+            | InstructionType::Pop(_)
+            | InstructionType::Call { .. }
+            | InstructionType::Rst(_)
+            | InstructionType::Return { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Decode an unprefixed opcode
+    ///
+    /// This is a lookup into a table built once from [`Self::decode_fields`].
+    pub fn decode(opcode: u8) -> Result<Self, DecodeError> {
+        static TABLE: OnceLock<[Result<Instruction, DecodeError>; 256]> = OnceLock::new();
+
+        let table = TABLE.get_or_init(|| std::array::from_fn(|op| Self::decode_fields(op as u8)));
+        table[opcode as usize]
+    }
+
+    /// Decode the opcode following a [`CB_PREFIX`]
+    pub fn decode_cb(opcode: u8) -> Result<Self, DecodeError> {
+        static TABLE: OnceLock<[Option<Instruction>; 256]> = OnceLock::new();
+
+        let table =
+            TABLE.get_or_init(|| std::array::from_fn(|op| Self::decode_cb_fields(op as u8)));
+
+        match table[opcode as usize] {
+            Some(instruction) => Ok(instruction),
+            None => Err(DecodeError::UnknownOpcode { opcode, cb: true }),
+        }
+    }
+
+    /// Decode an unprefixed opcode from its bit fields
+    ///
+    /// Opcodes are split into the fields `x` (bits 7-6), `y` (bits 5-3) and
+    /// `z` (bits 2-0), with `y` further split into `p` (bits 5-4) and `q`
+    /// (bit 3).
+    ///
+    /// Arms are tried in order, so a single opcode carved out of a wider
+    /// pattern, like NOP or HALT, must come before the arm that would
+    /// otherwise capture it. Loads are built with [`Instruction::try_load`],
+    /// so an arm producing an impossible operand pair decodes to
+    /// [`DecodeError::InvalidLoadOperands`] in every build.
+    fn decode_fields(opcode: u8) -> Result<Self, DecodeError> {
+        let x = opcode >> 6;
+        let y = (opcode >> 3) & 0b111;
+        let z = opcode & 0b111;
+        let p = y >> 1;
+        let q = y & 0b1;
+        let unknown = DecodeError::UnknownOpcode { opcode, cb: false };
+
+        let instruction = match (x, y, z, p, q) {
+            (0, 0, 0, _, _) => Instruction::nop(),
+
+            (0, 1, 0, _, _) => Instruction::store_sp(),
+            (0, 2, 0, _, _) => Instruction::stop(),
+            (0, 0..=3, 7, _, _) => {
+                Instruction::rotate_a(RotateOp::try_from(y).map_err(|_| unknown)?)
+            }
+            (0, 4, 7, _, _) => Instruction::daa(),
+            (0, 5, 7, _, _) => Instruction::cpl(),
+            (0, 6, 7, _, _) => Instruction::scf(),
+            (0, 7, 7, _, _) => Instruction::ccf(),
+            (0, 3, 0, _, _) => Instruction::jump_relative(None),
+            (0, 4..=7, 0, _, _) => {
+                Instruction::jump_relative(Some(Condition::try_from(y - 4).map_err(|_| unknown)?))
+            }
+
+            (0, _, 1, p, 0) => {
+                Instruction::try_load(Operand::from_rp_table(p), Operand::Immediate16)?
+            }
+            (0, _, 1, p, 1) => Instruction::arith16(RP_TABLE[p as usize]),
+
+            (0, _, 2, p, q) => {
+                let a = Operand::Register8(Register8::A);
+                let (indirect, followup) = match p {
+                    0 => (Operand::Indirect(Register16::BC), None),
+                    1 => (Operand::Indirect(Register16::DE), None),
+                    2 => (Operand::Indirect(Register16::HL), Some(FollowUp::Inc)),
+                    _ => (Operand::Indirect(Register16::HL), Some(FollowUp::Dec)),
+                };
+                let (dst, src) = if q == 0 { (indirect, a) } else { (a, indirect) };
+
+                match followup {
+                    Some(followup) => Instruction::load_hl(dst, src, followup),
+                    None => Instruction::try_load(dst, src)?,
+                }
+            }
+
+            (0, _, 3, p, 0) => Instruction::inc(Operand::from_rp_table(p)),
+            (0, _, 3, p, 1) => Instruction::dec(Operand::from_rp_table(p)),
+            (0, _, 4, _, _) => Instruction::inc(Operand::from_r_table(y)),
+            (0, _, 5, _, _) => Instruction::dec(Operand::from_r_table(y)),
+            (0, _, 6, _, _) => {
+                Instruction::try_load(Operand::from_r_table(y), Operand::Immediate8)?
+            }
+
+            // LD (HL),(HL) would be nonsensical, its encoding is HALT instead
+            (1, 6, 6, _, _) => Instruction::halt(),
+            (1, _, _, _, _) => {
+                Instruction::try_load(Operand::from_r_table(y), Operand::from_r_table(z))?
+            }
+
+            (2, _, _, _, _) => Instruction::arith8(
+                ArithOp::try_from(y).map_err(|_| unknown)?,
+                Operand::from_r_table(z),
+            ),
+
+            (3, 0..=3, 2, _, _) => {
+                Instruction::jump(Some(Condition::try_from(y).map_err(|_| unknown)?))
+            }
+            (3, 0, 3, _, _) => Instruction::jump(None),
+            (3, 5, 1, _, _) => Instruction::jump_hl(),
+
+            (3, 0..=3, 4, _, _) => {
+                Instruction::call(Some(Condition::try_from(y).map_err(|_| unknown)?))
+            }
+            (3, 1, 5, _, _) => Instruction::call(None),
+
+            (3, 0..=3, 0, _, _) => {
+                Instruction::ret(Some(Condition::try_from(y).map_err(|_| unknown)?))
+            }
+            (3, 1, 1, _, _) => Instruction::ret(None),
+            (3, 3, 1, _, _) => Instruction::reti(),
+
+            (3, _, 7, _, _) => Instruction::rst(y as u16 * 8),
+
+            (3, 4, 0, _, _) => {
+                Instruction::try_load(Operand::HighImmediate8, Operand::Register8(Register8::A))?
+            }
+            (3, 6, 0, _, _) => {
+                Instruction::try_load(Operand::Register8(Register8::A), Operand::HighImmediate8)?
+            }
+            (3, 4, 2, _, _) => {
+                Instruction::try_load(Operand::HighC, Operand::Register8(Register8::A))?
+            }
+            (3, 6, 2, _, _) => {
+                Instruction::try_load(Operand::Register8(Register8::A), Operand::HighC)?
+            }
+
+            (3, 5, 0, _, _) => Instruction::add_sp(),
+            (3, 7, 0, _, _) => Instruction::load_hl_sp(),
+            (3, 7, 1, _, _) => Instruction::try_load(
+                Operand::Register16(Register16::SP),
+                Operand::Register16(Register16::HL),
+            )?,
+
+            (3, 5, 2, _, _) => Instruction::try_load(
+                Operand::IndirectImmediate16,
+                Operand::Register8(Register8::A),
+            )?,
+            (3, 7, 2, _, _) => Instruction::try_load(
+                Operand::Register8(Register8::A),
+                Operand::IndirectImmediate16,
+            )?,
+
+            (3, _, 1, p, 0) => Instruction::pop(RP2_TABLE[p as usize]),
+            (3, _, 5, p, 0) => Instruction::push(RP2_TABLE[p as usize]),
+
+            (3, _, 6, _, _) => Instruction::arith8(
+                ArithOp::try_from(y).map_err(|_| unknown)?,
+                Operand::Immediate8,
+            ),
+
+            (3, 6, 3, _, _) => Instruction::di(),
+            (3, 7, 3, _, _) => Instruction::ei(),
+
+            _ => return Err(unknown),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Decode a CB-prefixed opcode from its bit fields
+    ///
+    /// Uses the same `x`, `y` and `z` fields as [`Self::decode_fields`], `z`
+    /// always selects the operand from [`R_TABLE`].
+    fn decode_cb_fields(opcode: u8) -> Option<Self> {
+        let x = opcode >> 6;
+        let y = (opcode >> 3) & 0b111;
+        let z = opcode & 0b111;
+
+        let operand = Operand::from_r_table(z);
+
+        let instruction = match (x, y) {
+            (0, 0..=3) => Instruction::rotate(RotateOp::try_from(y).ok()?, operand),
+            (0, 4) => Instruction::shift(ShiftOp::Sla, operand),
+            (0, 5) => Instruction::shift(ShiftOp::Sra, operand),
+            (0, 6) => Instruction::swap(operand),
+            (0, 7) => Instruction::shift(ShiftOp::Srl, operand),
+            (1, _) => Instruction::bit(y, operand),
+            (2, _) => Instruction::res_bit(y, operand),
+            (3, _) => Instruction::set_bit(y, operand),
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register8(reg) => write!(f, "{reg:?}"),
+            Operand::Register16(reg) => write!(f, "{reg:?}"),
+            Operand::Indirect(reg) => write!(f, "({reg:?})"),
+            Operand::Immediate8 => f.write_str("d8"),
+            Operand::Immediate16 => f.write_str("d16"),
+            Operand::IndirectImmediate16 => f.write_str("(a16)"),
+            Operand::HighImmediate8 => f.write_str("(a8)"),
+            Operand::HighC => f.write_str("(C)"),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl fmt::Display for InstructionType {
+    /// Standard mnemonic form, e.g. `LD B,C`, `ADD A,(HL)` or `JR NZ,r8`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(f, &[])
+    }
+}
+
+impl InstructionType {
+    /// Mnemonic form with immediates taken from `operands`, the bytes
+    /// following the opcode, e.g. `LD A,42H` or `JR NZ,-2`
+    ///
+    /// An immediate missing from `operands` is shown as a placeholder such as
+    /// `d8` or `a16`.
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, operands: &[u8]) -> fmt::Result {
+        let displacement = operands.first().map(|&e| format!("{:+}", e as i8));
+        let address = word_operand(operands).map(|nn| format!("{nn:04X}H"));
+
+        match self {
+            InstructionType::Nop => f.write_str("NOP"),
+            InstructionType::Halt => f.write_str("HALT"),
+            InstructionType::Stop => f.write_str("STOP"),
+            InstructionType::DisableInterrupts => f.write_str("DI"),
+            InstructionType::EnableInterrupts => f.write_str("EI"),
+            InstructionType::Load { dst, src, followup } => {
+                let operand = |operand: &Operand| match (operand, followup) {
+                    (Operand::Indirect(_), Some(FollowUp::Inc)) => "(HL+)".to_string(),
+                    (Operand::Indirect(_), Some(FollowUp::Dec)) => "(HL-)".to_string(),
+                    _ => operand_text(operand, operands),
+                };
+                let mnemonic = match (dst, src) {
+                    (Operand::HighImmediate8, _) | (_, Operand::HighImmediate8) => "LDH",
+                    _ => "LD",
+                };
+                write!(f, "{mnemonic} {},{}", operand(dst), operand(src))
+            }
+            InstructionType::LoadHlSp => match displacement {
+                Some(e) => write!(f, "LD HL,SP{e}"),
+                None => f.write_str("LD HL,SP+r8"),
+            },
+            InstructionType::AddSp => {
+                write!(f, "ADD SP,{}", displacement.as_deref().unwrap_or("r8"))
+            }
+            InstructionType::StoreSp => {
+                write!(f, "LD ({}),SP", address.as_deref().unwrap_or("a16"))
+            }
+            InstructionType::Push(reg) => write!(f, "PUSH {reg:?}"),
+            InstructionType::Pop(reg) => write!(f, "POP {reg:?}"),
+            InstructionType::JumpRelative { condition } => fmt_branch(
+                f,
+                "JR",
+                *condition,
+                Some(displacement.as_deref().unwrap_or("r8")),
+            ),
+            InstructionType::Jump { condition } => fmt_branch(
+                f,
+                "JP",
+                *condition,
+                Some(address.as_deref().unwrap_or("a16")),
+            ),
+            InstructionType::JumpHl => f.write_str("JP (HL)"),
+            InstructionType::Call { condition } => fmt_branch(
+                f,
+                "CALL",
+                *condition,
+                Some(address.as_deref().unwrap_or("a16")),
+            ),
+            InstructionType::Rst(vector) => write!(f, "RST {vector:02X}H"),
+            InstructionType::Return { reti: true, .. } => f.write_str("RETI"),
+            InstructionType::Return { condition, .. } => fmt_branch(f, "RET", *condition, None),
+            InstructionType::Inc(operand) => write!(f, "INC {operand}"),
+            InstructionType::Dec(operand) => write!(f, "DEC {operand}"),
+            InstructionType::RotateA(op) => write!(f, "{}A", op.mnemonic()),
+            InstructionType::Daa => f.write_str("DAA"),
+            InstructionType::Cpl => f.write_str("CPL"),
+            InstructionType::Scf => f.write_str("SCF"),
+            InstructionType::Ccf => f.write_str("CCF"),
+            InstructionType::Arith16(src) => write!(f, "ADD HL,{src:?}"),
+            InstructionType::Arith8 { op, src } => {
+                let src = operand_text(src, operands);
+                match op {
+                    ArithOp::Add | ArithOp::Adc | ArithOp::Sbc => {
+                        write!(f, "{} A,{src}", op.mnemonic())
+                    }
+                    _ => write!(f, "{} {src}", op.mnemonic()),
+                }
+            }
+            InstructionType::Rotate { op, operand } => write!(f, "{} {operand}", op.mnemonic()),
+            InstructionType::Shift { op, operand } => write!(f, "{} {operand}", op.mnemonic()),
+            InstructionType::Swap(operand) => write!(f, "SWAP {operand}"),
+            InstructionType::Bit { bit, operand } => write!(f, "BIT {bit},{operand}"),
+            InstructionType::ResBit { bit, operand } => write!(f, "RES {bit},{operand}"),
+            InstructionType::SetBit { bit, operand } => write!(f, "SET {bit},{operand}"),
+        }
+    }
+}
+
+/// The little-endian word at the start of `operands`, if there is one
+fn word_operand(operands: &[u8]) -> Option<u16> {
+    let &[lo, hi, ..] = operands else {
+        return None;
+    };
+    Some(u16::from_le_bytes([lo, hi]))
+}
+
+/// `operand` with any immediate it stands for read from `operands`
+fn operand_text(operand: &Operand, operands: &[u8]) -> String {
+    let byte = operands.first();
+    let word = word_operand(operands);
+
+    match (operand, byte, word) {
+        (Operand::Immediate8, Some(n), _) => format!("{n:02X}H"),
+        (Operand::Immediate16, _, Some(nn)) => format!("{nn:04X}H"),
+        (Operand::IndirectImmediate16, _, Some(nn)) => format!("({nn:04X}H)"),
+        (Operand::HighImmediate8, Some(n), _) => format!("(FF{n:02X}H)"),
+        _ => operand.to_string(),
+    }
+}
+
+/// A branch mnemonic followed by its condition and target, if any
+fn fmt_branch(
+    f: &mut fmt::Formatter<'_>,
+    mnemonic: &str,
+    condition: Option<Condition>,
+    target: Option<&str>,
+) -> fmt::Result {
+    match (condition, target) {
+        (Some(condition), Some(target)) => write!(f, "{mnemonic} {condition},{target}"),
+        (Some(condition), None) => write!(f, "{mnemonic} {condition}"),
+        (None, Some(target)) => write!(f, "{mnemonic} {target}"),
+        (None, None) => f.write_str(mnemonic),
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.itype.fmt(f)
+    }
+}
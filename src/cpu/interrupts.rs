@@ -0,0 +1,40 @@
+use crate::memory::Address;
+
+/// IE, the interrupt-enable register
+pub const INTERRUPT_ENABLE: Address = Address(0xFFFF);
+
+/// IF, the interrupt-flag (request) register
+pub const INTERRUPT_FLAG: Address = Address(0xFF0F);
+
+/// Handler addresses indexed by IE/IF bit, lowest bit has highest priority
+pub const VECTORS: [u16; 5] = [0x0040, 0x0048, 0x0050, 0x0058, 0x0060];
+
+/// Interrupt sources, highest priority first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    /// Every source, in priority order
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::Stat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    /// The source's bit in IE and IF
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    pub fn vector(self) -> u16 {
+        VECTORS[self as usize]
+    }
+}
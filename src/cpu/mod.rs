@@ -0,0 +1,1072 @@
+pub mod instructions;
+pub mod interrupts;
+pub mod registers;
+pub mod rewind;
+pub mod state;
+pub mod watch;
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use thiserror::Error;
+
+use crate::joypad::Button;
+use crate::memory::{Address, Memory, ROM_SIZE};
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::register;
+use crate::timer::DIV;
+
+use instructions::{
+    ArithOp, Condition, FollowUp, Instruction, InstructionType, Operand, RotateOp, ShiftOp,
+    CB_PREFIX,
+};
+use interrupts::Interrupt;
+use registers::{Flag, Registers};
+use rewind::RewindBuffer;
+use watch::{WatchHit, WatchKind};
+
+/// Machine cycles in one frame, 154 lines of 456 clocks
+pub const CYCLES_PER_FRAME: u64 = 17556;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CpuError {
+    #[error("unsupported instruction: {0:?}")]
+    UnsupportedInstruction(InstructionType),
+    #[error("stack overflow: pushing with SP={sp:#06X} would wrap below 0x0000")]
+    StackOverflow { sp: u16 },
+    #[error("stack underflow: popping with SP={sp:#06X} would wrap past 0xFFFF")]
+    StackUnderflow { sp: u16 },
+    #[error("no earlier state to rewind to")]
+    NothingToRewind,
+    #[error("ROM of {0} bytes doesn't fit the {ROM_SIZE:#X} byte ROM region")]
+    RomTooLarge(usize),
+    #[error("no cartridge inserted")]
+    NoCartridge,
+}
+
+/// Something that can carry out an instruction of type `I`
+pub trait Execute<I> {
+    /// Run `instruction`, returning the machine cycles it took
+    fn execute(&mut self, instruction: I) -> Result<u8>;
+}
+
+#[derive(Default)]
+pub struct Cpu {
+    registers: Registers,
+    memory: Memory,
+    halted: bool,
+    /// Set by HALT with IME clear and an interrupt already pending, the next
+    /// opcode fetch doesn't advance PC
+    halt_bug: bool,
+    stopped: bool,
+    /// Interrupt master enable
+    ime: bool,
+    /// Set by EI, IME turns on once the following instruction has run
+    ime_pending: bool,
+    /// Machine cycles elapsed since power on
+    cycles: u64,
+    /// Bus reads and writes made by the CPU since power on
+    memory_accesses: u64,
+    rewind: Option<RewindBuffer>,
+    /// States captured before each step, for [`Cpu::step_back`]
+    step_history: Option<RewindBuffer>,
+    /// Receives a Gameboy Doctor line before each instruction
+    trace: Option<Box<dyn Write>>,
+    breakpoints: HashSet<Address>,
+    watchpoints: HashMap<Address, WatchKind>,
+    /// First watchpoint hit since it was last taken
+    watch_hit: Option<WatchHit>,
+    /// PC at the start of the current step, for reporting watchpoint hits
+    instruction_pc: u16,
+}
+
+impl Cpu {
+    /// A CPU starting from the given register and memory state
+    ///
+    /// ```
+    /// use gaemboi::cpu::registers::Registers;
+    /// use gaemboi::cpu::Cpu;
+    /// use gaemboi::memory::{Address, Memory};
+    /// use gaemboi::register;
+    ///
+    /// let mut registers = Registers::default();
+    /// registers.write(register!(HL), 0xC000);
+    /// registers.write(register!(A), 0x42);
+    ///
+    /// let mut memory = Memory::default();
+    /// // LD (HL),A
+    /// memory.write_byte(Address(0x0000), 0x77);
+    ///
+    /// let mut cpu = Cpu::new(registers, memory);
+    /// cpu.step().unwrap();
+    ///
+    /// assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x42);
+    /// assert_eq!(cpu.registers().fetch(register!(PC)), 0x0001);
+    /// ```
+    pub fn new(registers: Registers, memory: Memory) -> Self {
+        Self {
+            registers,
+            memory,
+            ..Self::default()
+        }
+    }
+
+    /// Read a ROM image from `path` into a fresh CPU, see [`Cpu::load_rom`]
+    pub fn from_rom_path(path: &Path) -> Result<Self> {
+        let rom = std::fs::read(path)
+            .with_context(|| format!("failed to read ROM {}", path.display()))?;
+
+        let mut cpu = Self::default();
+        cpu.load_rom(&rom)?;
+        Ok(cpu)
+    }
+
+    /// Copy a ROM image into the ROM region starting at `0x0000`
+    ///
+    /// PC is left alone. Images bigger than the unbanked ROM region are
+    /// rejected, those need inserting as a cartridge.
+    pub fn load_rom(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() > ROM_SIZE {
+            bail!(CpuError::RomTooLarge(bytes.len()));
+        }
+
+        self.memory.write(Address(0x0000), bytes)?;
+        Ok(())
+    }
+
+    /// Copy `bytes` to the start of memory and begin execution at `entry`
+    ///
+    /// No cartridge header is required, which allows running raw test
+    /// payloads and homebrew that doesn't start at `0x0100`. The image is
+    /// bounded by the ROM region as for [`Cpu::load_rom`].
+    pub fn load_rom_with_entry(&mut self, bytes: &[u8], entry: Address) -> Result<()> {
+        self.load_rom(bytes)?;
+        self.registers.write(register!(PC), entry.0);
+        Ok(())
+    }
+
+    /// The inserted cartridge's external RAM, across all banks, for writing
+    /// out as a `.sav` file
+    ///
+    /// Empty without a cartridge or if the cartridge has no RAM.
+    pub fn dump_cartridge_ram(&self) -> Vec<u8> {
+        self.memory
+            .cartridge()
+            .map_or_else(Vec::new, |cartridge| cartridge.ram().to_vec())
+    }
+
+    /// Restore external RAM saved by [`Cpu::dump_cartridge_ram`], which must
+    /// be the size the cartridge header declares
+    pub fn load_cartridge_ram(&mut self, ram: &[u8]) -> Result<()> {
+        let cartridge = self.memory.cartridge_mut().ok_or(CpuError::NoCartridge)?;
+        cartridge.load_ram(ram)?;
+        Ok(())
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Raise `interrupt` in IF, to be serviced once enabled in IE and IME
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.memory.request_interrupt(interrupt);
+    }
+
+    /// Press or release `button`
+    ///
+    /// A press that pulls a selected P1 line low requests the joypad
+    /// interrupt and ends STOP mode.
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if self.memory.set_button(button, pressed) {
+            self.stopped = false;
+        }
+    }
+
+    /// The last frame drawn, as shades 0 (lightest) to 3, row by row
+    pub fn framebuffer(&self) -> &[u8; SCREEN_WIDTH * SCREEN_HEIGHT] {
+        self.memory.ppu().framebuffer()
+    }
+
+    /// Text sent over the serial port, where test ROMs report their results
+    pub fn serial_output(&self) -> &str {
+        self.memory.serial().output()
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.registers.fetch(register!(PC))
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.registers.fetch(register!(SP))
+    }
+
+    /// Whether a HALT instruction has suspended execution
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether a STOP instruction has suspended execution
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn ime(&self) -> bool {
+        self.ime
+    }
+
+    /// The DIV register, which keeps counting while halted or stopped
+    pub fn read_div(&self) -> u8 {
+        self.memory.read_byte(DIV)
+    }
+
+    /// Machine cycles elapsed since power on
+    pub fn cycles_elapsed(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Bus reads and writes made by the CPU since power on, including
+    /// opcode and operand fetches
+    pub fn memory_accesses(&self) -> u64 {
+        self.memory_accesses
+    }
+
+    /// The top `depth` words of the stack, most recently pushed first
+    pub fn stack_dump(&self, depth: usize) -> Vec<u16> {
+        (0..depth)
+            .map(|i| {
+                let offset = (i as u16).wrapping_mul(2);
+                self.memory.read_word(Address(self.sp()) + offset)
+            })
+            .collect()
+    }
+
+    /// Fetch, decode and execute the instruction at PC, returning the
+    /// machine cycles it took
+    ///
+    /// Pending interrupts are serviced instead if IME is set. IME is checked
+    /// before the EI latch is applied, so the instruction following EI always
+    /// runs before any interrupt is taken.
+    pub fn step(&mut self) -> Result<u8> {
+        let (cycles, _) = self.step_timed()?;
+        Ok(cycles)
+    }
+
+    /// Like [`Cpu::step`], but returning the instruction that ran
+    ///
+    /// `None` if the step serviced an interrupt or idled while halted or
+    /// stopped instead.
+    pub fn step_instruction(&mut self) -> Result<Option<Instruction>> {
+        let (_, instruction) = self.step_timed()?;
+        Ok(instruction)
+    }
+
+    /// Run until at least `cycles` machine cycles have elapsed, returning
+    /// how many actually did
+    ///
+    /// While halted with nothing pending, time is fast-forwarded straight to
+    /// the next event that could wake the CPU instead of idling cycle by cycle.
+    /// Stops early, before executing it, if PC reaches a breakpoint, or after
+    /// the instruction that triggers a watchpoint.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> Result<u64> {
+        let mut elapsed = 0;
+
+        while elapsed < cycles {
+            if self.halted && !self.interrupt_pending() {
+                let remaining = cycles - elapsed;
+                let skip = self
+                    .memory
+                    .cycles_until_event()
+                    .map_or(remaining, |until| until.min(remaining))
+                    .max(1);
+
+                self.memory.tick(skip);
+                self.cycles += skip;
+                elapsed += skip;
+                continue;
+            }
+
+            elapsed += self.step()? as u64;
+
+            if self.at_breakpoint() || self.watch_hit.is_some() {
+                break;
+            }
+        }
+
+        Ok(elapsed)
+    }
+
+    /// Step until PC reaches a breakpoint, returning its address before the
+    /// instruction there executes
+    ///
+    /// Also returns, with the PC following the triggering instruction, when a
+    /// watchpoint fires. At least one instruction is always run, so calling
+    /// this again while stopped on a breakpoint continues past it.
+    pub fn run_until_break(&mut self) -> Result<Address> {
+        loop {
+            self.step()?;
+
+            if self.at_breakpoint() || self.watch_hit.is_some() {
+                return Ok(Address(self.pc()));
+            }
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, addr: Address) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: Address) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn add_watchpoint(&mut self, addr: Address, kind: WatchKind) {
+        self.watchpoints.insert(addr, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: Address) {
+        self.watchpoints.remove(&addr);
+    }
+
+    /// The first watchpoint hit since this was last called, if any
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&Address(self.pc()))
+    }
+
+    /// Run for one frame's worth of cycles, capturing a rewind state if
+    /// rewinding is enabled and one is due
+    pub fn run_frame(&mut self) -> Result<u64> {
+        let elapsed = self.run_for_cycles(CYCLES_PER_FRAME)?;
+
+        if self
+            .rewind
+            .as_mut()
+            .is_some_and(RewindBuffer::frame_finished)
+        {
+            let state = self.save_state();
+            if let Some(rewind) = self.rewind.as_mut() {
+                rewind.push(state);
+            }
+        }
+
+        Ok(elapsed)
+    }
+
+    /// Keep the last `capacity` states, captured every `interval` frames,
+    /// for [`Cpu::rewind`]
+    pub fn enable_rewind(&mut self, capacity: usize, interval: u32) {
+        self.rewind = Some(RewindBuffer::new(capacity, interval));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    /// Keep the state from before each of the last `depth` steps, for
+    /// [`Cpu::step_back`]
+    pub fn enable_step_history(&mut self, depth: usize) {
+        self.step_history = Some(RewindBuffer::new(depth, 1));
+    }
+
+    pub fn disable_step_history(&mut self) {
+        self.step_history = None;
+    }
+
+    /// Undo the most recent step still in the step history
+    pub fn step_back(&mut self) -> Result<()> {
+        let state = self
+            .step_history
+            .as_mut()
+            .and_then(RewindBuffer::pop)
+            .ok_or(CpuError::NothingToRewind)?;
+
+        self.load_state(&state)
+    }
+
+    /// Write the registers and the four bytes at PC to `writer` before every
+    /// instruction, in the format Gameboy Doctor compares against
+    pub fn enable_trace(&mut self, writer: Box<dyn Write>) {
+        self.trace = Some(writer);
+    }
+
+    pub fn disable_trace(&mut self) {
+        self.trace = None;
+    }
+
+    /// Restore the most recently captured rewind state, discarding it
+    pub fn rewind(&mut self) -> Result<()> {
+        let state = self
+            .rewind
+            .as_mut()
+            .and_then(RewindBuffer::pop)
+            .ok_or(CpuError::NothingToRewind)?;
+
+        self.load_state(&state)
+    }
+
+    /// Record the state for [`Cpu::step_back`], step the CPU and then clock
+    /// the rest of the hardware to match
+    fn step_timed(&mut self) -> Result<(u8, Option<Instruction>)> {
+        if self.step_history.is_some() {
+            let state = self.save_state();
+            if let Some(history) = self.step_history.as_mut() {
+                history.push(state);
+            }
+        }
+
+        let (cycles, instruction) = self.step_cpu()?;
+
+        self.memory.tick(cycles as u64);
+        self.cycles += cycles as u64;
+
+        Ok((cycles, instruction))
+    }
+
+    fn step_cpu(&mut self) -> Result<(u8, Option<Instruction>)> {
+        self.instruction_pc = self.pc();
+
+        if self.service_interrupt()? {
+            return Ok((5, None));
+        }
+
+        if self.halted || self.stopped {
+            return Ok((1, None));
+        }
+
+        if std::mem::take(&mut self.ime_pending) {
+            self.ime = true;
+        }
+
+        self.write_trace()?;
+
+        let accesses_before = self.memory_accesses;
+
+        let opcode = if std::mem::take(&mut self.halt_bug) {
+            self.read_byte(Address(self.pc()))
+        } else {
+            self.fetch_immediate8()
+        };
+        let instruction = match opcode {
+            CB_PREFIX => Instruction::decode_cb(self.fetch_immediate8())?,
+            _ => Instruction::decode(opcode)?,
+        };
+        let cycles = self.execute(instruction)?;
+
+        // Every bus access takes a machine cycle, anything left over is
+        // internal work, so an instruction can't access memory more often
+        // than its declared cost allows
+        debug_assert!(
+            self.memory_accesses - accesses_before <= cycles as u64,
+            "{instruction:?} made {} accesses in {cycles} cycles",
+            self.memory_accesses - accesses_before
+        );
+
+        Ok((cycles, Some(instruction)))
+    }
+
+    /// Emit a trace line like
+    /// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`
+    fn write_trace(&mut self) -> Result<()> {
+        let Some(trace) = self.trace.as_mut() else {
+            return Ok(());
+        };
+
+        let r = &self.registers;
+        let pc = r.fetch(register!(PC));
+        let [m0, m1, m2, m3] =
+            std::array::from_fn(|i| self.memory.read_byte(Address(pc) + i as u16));
+
+        writeln!(
+            trace,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} \
+             SP:{:04X} PC:{pc:04X} PCMEM:{m0:02X},{m1:02X},{m2:02X},{m3:02X}",
+            r.fetch(register!(A)),
+            r.fetch(register!(F)),
+            r.fetch(register!(B)),
+            r.fetch(register!(C)),
+            r.fetch(register!(D)),
+            r.fetch(register!(E)),
+            r.fetch(register!(H)),
+            r.fetch(register!(L)),
+            r.fetch(register!(SP)),
+        )?;
+
+        Ok(())
+    }
+
+    fn execute_instruction(&mut self, instruction: Instruction) -> Result<u8> {
+        let mut taken = true;
+
+        match *instruction.itype() {
+            InstructionType::Nop => {}
+            InstructionType::Halt => {
+                if !self.ime && self.interrupt_pending() {
+                    self.halt_bug = true;
+                } else {
+                    self.halted = true;
+                }
+            }
+            InstructionType::Stop => {
+                // Skip the padding byte without reading it
+                self.registers.inc(register!(PC));
+                self.stopped = true;
+            }
+            InstructionType::DisableInterrupts => {
+                self.ime = false;
+                self.ime_pending = false;
+            }
+            InstructionType::EnableInterrupts => self.ime_pending = true,
+            InstructionType::Load {
+                dst: Operand::Register16(reg),
+                src,
+                ..
+            } => {
+                let value = self.fetch_word_from_operand(src)?;
+                self.registers.write(reg, value);
+            }
+            InstructionType::Load { dst, src, followup } => {
+                let value = self.fetch_byte_from_operand(src)?;
+                self.write_byte_to_operand(dst, value)?;
+
+                match followup {
+                    Some(FollowUp::Inc) => self.registers.inc(register!(HL)),
+                    Some(FollowUp::Dec) => self.registers.dec(register!(HL)),
+                    None => {}
+                }
+            }
+            InstructionType::LoadHlSp => {
+                let offset = self.fetch_signed_immediate8();
+                let value = self.sp_plus_offset(offset);
+                self.registers.write(register!(HL), value);
+            }
+            InstructionType::AddSp => {
+                let offset = self.fetch_signed_immediate8();
+                let value = self.sp_plus_offset(offset);
+                self.registers.write(register!(SP), value);
+            }
+            InstructionType::StoreSp => {
+                let addr = Address(self.fetch_immediate16());
+                self.write_word(addr, self.sp());
+            }
+            InstructionType::Push(reg) => {
+                let value = self.registers.fetch(reg);
+                self.push_word(value)?;
+            }
+            InstructionType::Pop(reg) => {
+                let value = self.pop_word()?;
+                self.registers.write(reg, value);
+            }
+            InstructionType::JumpRelative { condition } => {
+                // The operand is consumed whether or not the branch is taken
+                let offset = self.fetch_signed_immediate8();
+                taken = self.condition_holds(condition);
+                if taken {
+                    let target = Address(self.pc()).offset(offset);
+                    self.registers.write(register!(PC), target.0);
+                }
+            }
+            InstructionType::Jump { condition } => {
+                let target = self.fetch_immediate16();
+                taken = self.condition_holds(condition);
+                if taken {
+                    self.registers.write(register!(PC), target);
+                }
+            }
+            InstructionType::JumpHl => {
+                let hl = self.registers.fetch(register!(HL));
+                self.registers.write(register!(PC), hl);
+            }
+            InstructionType::Call { condition } => {
+                let target = self.fetch_immediate16();
+                taken = self.condition_holds(condition);
+                if taken {
+                    let ret = self.pc();
+                    self.push_word(ret)?;
+                    self.registers.write(register!(PC), target);
+                }
+            }
+            InstructionType::Rst(vector) => {
+                let ret = self.pc();
+                self.push_word(ret)?;
+                self.registers.write(register!(PC), vector);
+            }
+            InstructionType::Return { condition, reti } => {
+                taken = self.condition_holds(condition);
+                if taken {
+                    let target = self.pop_word()?;
+                    self.registers.write(register!(PC), target);
+                }
+
+                if reti {
+                    self.ime = true;
+                }
+            }
+            InstructionType::Inc(Operand::Register16(reg)) => self.registers.inc(reg),
+            InstructionType::Dec(Operand::Register16(reg)) => self.registers.dec(reg),
+            InstructionType::Inc(operand) => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                let result = value.wrapping_add(1);
+
+                self.registers.set_flag(Flag::Zero, result == 0);
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers
+                    .set_flag(Flag::HalfCarry, value & 0x0F == 0x0F);
+
+                self.write_byte_to_operand(operand, result)?;
+            }
+            InstructionType::Dec(operand) => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                let result = value.wrapping_sub(1);
+
+                self.registers.set_flag(Flag::Zero, result == 0);
+                self.registers.set_flag(Flag::Sub, true);
+                self.registers
+                    .set_flag(Flag::HalfCarry, value & 0x0F == 0x00);
+
+                self.write_byte_to_operand(operand, result)?;
+            }
+            InstructionType::RotateA(op) => {
+                let a = self.registers.fetch(register!(A));
+                let result = self.rotate(op, a);
+                self.registers.set_flag(Flag::Zero, false);
+                self.registers.write(register!(A), result);
+            }
+            InstructionType::Daa => self.daa(),
+            InstructionType::Cpl => {
+                let a = self.registers.fetch(register!(A));
+                self.registers.write(register!(A), !a);
+                self.registers.set_flag(Flag::Sub, true);
+                self.registers.set_flag(Flag::HalfCarry, true);
+            }
+            InstructionType::Scf => {
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers.set_flag(Flag::HalfCarry, false);
+                self.registers.set_flag(Flag::Carry, true);
+            }
+            InstructionType::Ccf => {
+                let carry = self.registers.flag(Flag::Carry);
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers.set_flag(Flag::HalfCarry, false);
+                self.registers.set_flag(Flag::Carry, !carry);
+            }
+            InstructionType::Arith16(src) => {
+                let hl = self.registers.fetch(register!(HL));
+                let value = self.registers.fetch(src);
+                let (sum, carry) = hl.overflowing_add(value);
+
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers
+                    .set_flag(Flag::HalfCarry, (hl & 0x0FFF) + (value & 0x0FFF) > 0x0FFF);
+                self.registers.set_flag(Flag::Carry, carry);
+
+                self.registers.write(register!(HL), sum);
+            }
+            InstructionType::Arith8 { op, src } => {
+                let value = self.fetch_byte_from_operand(src)?;
+                self.arith8(op, value);
+            }
+            InstructionType::Rotate { op, operand } => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                let result = self.rotate(op, value);
+                self.write_byte_to_operand(operand, result)?;
+            }
+            InstructionType::Shift { op, operand } => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                let result = self.shift(op, value);
+                self.write_byte_to_operand(operand, result)?;
+            }
+            InstructionType::Swap(operand) => {
+                let result = self.fetch_byte_from_operand(operand)?.rotate_left(4);
+
+                self.registers.set_flag(Flag::Zero, result == 0);
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers.set_flag(Flag::HalfCarry, false);
+                self.registers.set_flag(Flag::Carry, false);
+
+                self.write_byte_to_operand(operand, result)?;
+            }
+            InstructionType::Bit { bit, operand } => {
+                let value = self.fetch_byte_from_operand(operand)?;
+
+                self.registers.set_flag(Flag::Zero, value & (1 << bit) == 0);
+                self.registers.set_flag(Flag::Sub, false);
+                self.registers.set_flag(Flag::HalfCarry, true);
+            }
+            InstructionType::ResBit { bit, operand } => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                self.write_byte_to_operand(operand, value & !(1 << bit))?;
+            }
+            InstructionType::SetBit { bit, operand } => {
+                let value = self.fetch_byte_from_operand(operand)?;
+                self.write_byte_to_operand(operand, value | 1 << bit)?;
+            }
+        }
+
+        if taken {
+            Ok(instruction.cycles())
+        } else {
+            Ok(instruction.cycles_not_taken())
+        }
+    }
+
+    /// Whether a branch on `condition` is taken, always true without one
+    fn condition_holds(&self, condition: Option<Condition>) -> bool {
+        condition.is_none_or(|condition| condition.is_met(&self.registers))
+    }
+
+    /// Adjust A so the last addition or subtraction of two BCD values gives
+    /// a BCD result, using N, H and C to tell what that operation was
+    fn daa(&mut self) {
+        let mut a = self.registers.fetch(register!(A));
+        let mut carry = self.registers.flag(Flag::Carry);
+        let half_carry = self.registers.flag(Flag::HalfCarry);
+
+        if self.registers.flag(Flag::Sub) {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if half_carry {
+                a = a.wrapping_sub(0x06);
+            }
+        } else {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if half_carry || a & 0x0F > 0x09 {
+                a = a.wrapping_add(0x06);
+            }
+        }
+
+        self.registers.set_flag(Flag::Zero, a == 0);
+        self.registers.set_flag(Flag::HalfCarry, false);
+        self.registers.set_flag(Flag::Carry, carry);
+        self.registers.write(register!(A), a);
+    }
+
+    /// SP plus a signed displacement, setting H and C from the unsigned
+    /// addition of the low bytes and clearing Z and N
+    fn sp_plus_offset(&mut self, offset: i8) -> u16 {
+        let sp = self.sp();
+        let low = sp as u8;
+        let byte = offset as u8;
+
+        self.registers.set_flag(Flag::Zero, false);
+        self.registers.set_flag(Flag::Sub, false);
+        self.registers
+            .set_flag(Flag::HalfCarry, (low & 0x0F) + (byte & 0x0F) > 0x0F);
+        self.registers
+            .set_flag(Flag::Carry, low.checked_add(byte).is_none());
+
+        sp.wrapping_add_signed(offset as i16)
+    }
+
+    /// Apply `op` to A and `value`, setting flags
+    fn arith8(&mut self, op: ArithOp, value: u8) {
+        let a = self.registers.fetch(register!(A));
+        let carry_in = match op {
+            ArithOp::Adc | ArithOp::Sbc => self.registers.flag(Flag::Carry) as u8,
+            _ => 0,
+        };
+
+        let (result, half_carry, carry) = match op {
+            ArithOp::Add | ArithOp::Adc => {
+                let sum = a as u16 + value as u16 + carry_in as u16;
+                let half = (a & 0x0F) + (value & 0x0F) + carry_in > 0x0F;
+                (sum as u8, half, sum > 0xFF)
+            }
+            ArithOp::Sub | ArithOp::Sbc | ArithOp::Cp => {
+                let diff = (a as u16)
+                    .wrapping_sub(value as u16)
+                    .wrapping_sub(carry_in as u16);
+                let half = (a & 0x0F) < (value & 0x0F) + carry_in;
+                let borrow = (a as u16) < value as u16 + carry_in as u16;
+                (diff as u8, half, borrow)
+            }
+            ArithOp::And => (a & value, true, false),
+            ArithOp::Xor => (a ^ value, false, false),
+            ArithOp::Or => (a | value, false, false),
+        };
+
+        let subtract = matches!(op, ArithOp::Sub | ArithOp::Sbc | ArithOp::Cp);
+
+        self.registers.set_flag(Flag::Zero, result == 0);
+        self.registers.set_flag(Flag::Sub, subtract);
+        self.registers.set_flag(Flag::HalfCarry, half_carry);
+        self.registers.set_flag(Flag::Carry, carry);
+
+        if op != ArithOp::Cp {
+            self.registers.write(register!(A), result);
+        }
+    }
+
+    /// Rotate `value` by one bit, setting flags, and return the result
+    fn rotate(&mut self, op: RotateOp, value: u8) -> u8 {
+        let carry_in = self.registers.flag(Flag::Carry) as u8;
+
+        let (result, carry) = match op {
+            RotateOp::Rlc => (value.rotate_left(1), value & 0x80 != 0),
+            RotateOp::Rrc => (value.rotate_right(1), value & 0x01 != 0),
+            RotateOp::Rl => (value << 1 | carry_in, value & 0x80 != 0),
+            RotateOp::Rr => (value >> 1 | carry_in << 7, value & 0x01 != 0),
+        };
+
+        self.registers.set_flag(Flag::Zero, result == 0);
+        self.registers.set_flag(Flag::Sub, false);
+        self.registers.set_flag(Flag::HalfCarry, false);
+        self.registers.set_flag(Flag::Carry, carry);
+
+        result
+    }
+
+    /// Shift `value` by one bit, setting flags, and return the result
+    fn shift(&mut self, op: ShiftOp, value: u8) -> u8 {
+        let (result, carry) = match op {
+            ShiftOp::Sla => (value << 1, value & 0x80 != 0),
+            ShiftOp::Sra => (value >> 1 | value & 0x80, value & 0x01 != 0),
+            ShiftOp::Srl => (value >> 1, value & 0x01 != 0),
+        };
+
+        self.registers.set_flag(Flag::Zero, result == 0);
+        self.registers.set_flag(Flag::Sub, false);
+        self.registers.set_flag(Flag::HalfCarry, false);
+        self.registers.set_flag(Flag::Carry, carry);
+
+        result
+    }
+
+    /// Whether any enabled interrupt is requested, regardless of IME
+    fn interrupt_pending(&self) -> bool {
+        let enabled = self.memory.read_byte(interrupts::INTERRUPT_ENABLE);
+        let requested = self.memory.read_byte(interrupts::INTERRUPT_FLAG);
+        enabled & requested & 0x1F != 0
+    }
+
+    /// Wake from HALT on any pending interrupt and, if IME allows, jump to
+    /// the highest priority handler. Returns whether a handler was entered.
+    fn service_interrupt(&mut self) -> Result<bool> {
+        let enabled = self.memory.read_byte(interrupts::INTERRUPT_ENABLE);
+        let requested = self.memory.read_byte(interrupts::INTERRUPT_FLAG);
+        let pending = enabled & requested & 0x1F;
+
+        if pending == 0 {
+            return Ok(false);
+        }
+
+        self.halted = false;
+
+        if !self.ime {
+            return Ok(false);
+        }
+
+        let interrupt = Interrupt::ALL
+            .into_iter()
+            .find(|interrupt| pending & interrupt.bit() != 0)
+            .expect("pending has a source bit set");
+        // Pushed first so a stack error leaves the request and IME in place
+        let pc = self.pc();
+        self.push_word(pc)?;
+
+        let requested = self.memory.read_byte(interrupts::INTERRUPT_FLAG);
+        self.memory
+            .write_byte(interrupts::INTERRUPT_FLAG, requested & !interrupt.bit());
+        self.ime = false;
+        self.registers.write(register!(PC), interrupt.vector());
+
+        Ok(true)
+    }
+
+    /// Push `value` onto the stack, high byte first
+    ///
+    /// Fails rather than splitting the word across both ends of the address
+    /// space.
+    fn push_word(&mut self, value: u16) -> Result<()> {
+        let [hi, lo] = value.to_be_bytes();
+
+        let sp = self.sp();
+        if sp == 0x0001 {
+            bail!(CpuError::StackOverflow { sp });
+        }
+
+        self.registers.dec(register!(SP));
+        self.write_byte(Address(self.sp()), hi);
+        self.registers.dec(register!(SP));
+        self.write_byte(Address(self.sp()), lo);
+        Ok(())
+    }
+
+    /// Pop a word off the stack, low byte first
+    ///
+    /// Fails rather than splitting the word across both ends of the address
+    /// space.
+    fn pop_word(&mut self) -> Result<u16> {
+        let sp = self.sp();
+        if sp == 0xFFFF {
+            bail!(CpuError::StackUnderflow { sp });
+        }
+
+        let value = self.read_word(Address(sp));
+        self.registers.write(register!(SP), sp.wrapping_add(2));
+
+        Ok(value)
+    }
+
+    /// Read a byte over the bus, counting the access
+    fn read_byte(&mut self, addr: Address) -> u8 {
+        self.memory_accesses += 1;
+        let value = self.memory.read_byte(addr);
+        self.check_watchpoint(addr, false, value);
+        value
+    }
+
+    /// Write a byte over the bus, counting the access
+    fn write_byte(&mut self, addr: Address, value: u8) {
+        self.memory_accesses += 1;
+        self.memory.write_byte(addr, value);
+        self.check_watchpoint(addr, true, value);
+    }
+
+    /// Record a hit if `addr` is watched for this kind of access
+    fn check_watchpoint(&mut self, addr: Address, write: bool, value: u8) {
+        if self.watch_hit.is_some() {
+            return;
+        }
+
+        if self
+            .watchpoints
+            .get(&addr)
+            .is_some_and(|kind| kind.matches(write))
+        {
+            self.watch_hit = Some(WatchHit {
+                pc: Address(self.instruction_pc),
+                addr,
+                kind: if write {
+                    WatchKind::Write
+                } else {
+                    WatchKind::Read
+                },
+                value,
+            });
+        }
+    }
+
+    /// Read a little-endian word over the bus as two counted accesses
+    fn read_word(&mut self, addr: Address) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr + 1)])
+    }
+
+    /// Write a little-endian word over the bus as two counted accesses
+    fn write_word(&mut self, addr: Address, value: u16) {
+        let [lo, hi] = value.to_le_bytes();
+        self.write_byte(addr, lo);
+        self.write_byte(addr + 1, hi);
+    }
+
+    /// Read the byte at PC and advance past it
+    fn fetch_immediate8(&mut self) -> u8 {
+        let pc = self.registers.fetch(register!(PC));
+        self.registers.inc(register!(PC));
+        self.read_byte(Address(pc))
+    }
+
+    /// Read the byte at PC as a two's complement displacement and advance
+    /// past it, as used by JR, ADD SP,e and LD HL,SP+e
+    fn fetch_signed_immediate8(&mut self) -> i8 {
+        self.fetch_immediate8() as i8
+    }
+
+    /// Read the little-endian word at PC and advance past it
+    fn fetch_immediate16(&mut self) -> u16 {
+        let pc = self.pc();
+        self.registers.write(register!(PC), pc.wrapping_add(2));
+        self.read_word(Address(pc))
+    }
+
+    fn fetch_byte_from_operand(&mut self, operand: Operand) -> Result<u8> {
+        let value = match operand {
+            Operand::Register8(reg) => self.registers.fetch(reg),
+            Operand::Indirect(reg) => self.read_byte(Address(self.registers.fetch(reg))),
+            Operand::Immediate8 => self.fetch_immediate8(),
+            Operand::IndirectImmediate16 => {
+                let addr = self.fetch_immediate16();
+                self.read_byte(Address(addr))
+            }
+            Operand::HighImmediate8 => {
+                let offset = self.fetch_immediate8();
+                self.read_byte(high_page(offset))
+            }
+            Operand::HighC => self.read_byte(high_page(self.registers.fetch(register!(C)))),
+            Operand::Register16(_) | Operand::Immediate16 => {
+                bail!("{operand:?} is not an 8-bit source")
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn fetch_word_from_operand(&mut self, operand: Operand) -> Result<u16> {
+        let value = match operand {
+            Operand::Register16(reg) => self.registers.fetch(reg),
+            Operand::Immediate16 => self.fetch_immediate16(),
+            _ => bail!("{operand:?} is not a 16-bit source"),
+        };
+
+        Ok(value)
+    }
+
+    fn write_byte_to_operand(&mut self, operand: Operand, value: u8) -> Result<()> {
+        match operand {
+            Operand::Register8(reg) => self.registers.write(reg, value),
+            Operand::Indirect(reg) => self.write_byte(Address(self.registers.fetch(reg)), value),
+            Operand::IndirectImmediate16 => {
+                let addr = self.fetch_immediate16();
+                self.write_byte(Address(addr), value)
+            }
+            Operand::HighImmediate8 => {
+                let offset = self.fetch_immediate8();
+                self.write_byte(high_page(offset), value)
+            }
+            Operand::HighC => {
+                let offset = self.registers.fetch(register!(C));
+                self.write_byte(high_page(offset), value)
+            }
+            Operand::Register16(_) | Operand::Immediate8 | Operand::Immediate16 => {
+                bail!("{operand:?} is not an 8-bit destination")
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Executes a decoded instruction as if it had just been fetched from PC
+///
+/// Operands are still read from PC, and neither the clock nor the timer is
+/// advanced, that's left to [`Cpu::step`].
+impl Execute<Instruction> for Cpu {
+    fn execute(&mut self, instruction: Instruction) -> Result<u8> {
+        self.execute_instruction(instruction)
+    }
+}
+
+/// Address of `offset` in the `0xFF00` I/O and high RAM page
+fn high_page(offset: u8) -> Address {
+    Address(0xFF00 | offset as u16)
+}
@@ -0,0 +1,281 @@
+use std::fmt::{self, Write};
+
+/// The eight 8-bit registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register8 {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+}
+
+/// The paired and 16-bit registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register16 {
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+}
+
+/// The flag bits held in the upper nibble of F
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flag {
+    /// Z, the result was zero
+    Zero,
+    /// N, the last operation was a subtraction
+    Sub,
+    /// H, carry out of (or borrow into) bit 3
+    HalfCarry,
+    /// C, carry out of (or borrow into) the top bit
+    Carry,
+}
+
+impl Flag {
+    /// Mask of this flag within F
+    pub fn bit(&self) -> u8 {
+        match self {
+            Flag::Zero => 1 << 7,
+            Flag::Sub => 1 << 6,
+            Flag::HalfCarry => 1 << 5,
+            Flag::Carry => 1 << 4,
+        }
+    }
+}
+
+/// Uniform access to a register of either width
+pub trait Register: Copy {
+    type Value;
+
+    fn fetch(self, registers: &Registers) -> Self::Value;
+    fn write(self, registers: &mut Registers, value: Self::Value);
+    fn inc(self, registers: &mut Registers);
+    fn dec(self, registers: &mut Registers);
+}
+
+/// Expands a bare register name to its `Register8`/`Register16` variant,
+/// e.g. `register!(A)` or `register!(HL)`
+#[macro_export]
+macro_rules! register {
+    (A) => {
+        $crate::cpu::registers::Register8::A
+    };
+    (F) => {
+        $crate::cpu::registers::Register8::F
+    };
+    (B) => {
+        $crate::cpu::registers::Register8::B
+    };
+    (C) => {
+        $crate::cpu::registers::Register8::C
+    };
+    (D) => {
+        $crate::cpu::registers::Register8::D
+    };
+    (E) => {
+        $crate::cpu::registers::Register8::E
+    };
+    (H) => {
+        $crate::cpu::registers::Register8::H
+    };
+    (L) => {
+        $crate::cpu::registers::Register8::L
+    };
+    (AF) => {
+        $crate::cpu::registers::Register16::AF
+    };
+    (BC) => {
+        $crate::cpu::registers::Register16::BC
+    };
+    (DE) => {
+        $crate::cpu::registers::Register16::DE
+    };
+    (HL) => {
+        $crate::cpu::registers::Register16::HL
+    };
+    (SP) => {
+        $crate::cpu::registers::Register16::SP
+    };
+    (PC) => {
+        $crate::cpu::registers::Register16::PC
+    };
+}
+
+/// CPU register file
+///
+/// F holds the flags in its upper nibble (ZNHC); the lower nibble does not
+/// exist on hardware and always reads back as zero.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct Registers {
+    a: u8,
+    f: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    h: u8,
+    l: u8,
+    sp: u16,
+    pc: u16,
+}
+
+impl Registers {
+    pub fn fetch<R: Register>(&self, reg: R) -> R::Value {
+        reg.fetch(self)
+    }
+
+    pub fn write<R: Register>(&mut self, reg: R, value: R::Value) {
+        reg.write(self, value)
+    }
+
+    pub fn inc<R: Register>(&mut self, reg: R) {
+        reg.inc(self)
+    }
+
+    pub fn dec<R: Register>(&mut self, reg: R) {
+        reg.dec(self)
+    }
+
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.f & flag.bit() != 0
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        if value {
+            self.f |= flag.bit();
+        } else {
+            self.f &= !flag.bit();
+        }
+    }
+}
+
+/// One line of hex, e.g.
+/// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D AF:01B0 BC:0013 DE:00D8 HL:014D SP:FFFE PC:0100 [Z-HC]`
+impl fmt::Debug for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} ",
+            self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l
+        )?;
+        write!(
+            f,
+            "AF:{:04X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X} PC:{:04X} [",
+            self.fetch(Register16::AF),
+            self.fetch(Register16::BC),
+            self.fetch(Register16::DE),
+            self.fetch(Register16::HL),
+            self.sp,
+            self.pc
+        )?;
+
+        for (flag, name) in [
+            (Flag::Zero, 'Z'),
+            (Flag::Sub, 'N'),
+            (Flag::HalfCarry, 'H'),
+            (Flag::Carry, 'C'),
+        ] {
+            f.write_char(if self.flag(flag) { name } else { '-' })?;
+        }
+
+        f.write_char(']')
+    }
+}
+
+impl Register for Register8 {
+    type Value = u8;
+
+    fn fetch(self, registers: &Registers) -> u8 {
+        match self {
+            Register8::A => registers.a,
+            Register8::F => registers.f,
+            Register8::B => registers.b,
+            Register8::C => registers.c,
+            Register8::D => registers.d,
+            Register8::E => registers.e,
+            Register8::H => registers.h,
+            Register8::L => registers.l,
+        }
+    }
+
+    fn write(self, registers: &mut Registers, value: u8) {
+        match self {
+            Register8::A => registers.a = value,
+            Register8::F => registers.f = value & 0xF0,
+            Register8::B => registers.b = value,
+            Register8::C => registers.c = value,
+            Register8::D => registers.d = value,
+            Register8::E => registers.e = value,
+            Register8::H => registers.h = value,
+            Register8::L => registers.l = value,
+        }
+    }
+
+    fn inc(self, registers: &mut Registers) {
+        let value = self.fetch(registers).wrapping_add(1);
+        self.write(registers, value);
+    }
+
+    fn dec(self, registers: &mut Registers) {
+        let value = self.fetch(registers).wrapping_sub(1);
+        self.write(registers, value);
+    }
+}
+
+impl Register for Register16 {
+    type Value = u16;
+
+    fn fetch(self, registers: &Registers) -> u16 {
+        let pair = |hi: u8, lo: u8| u16::from_be_bytes([hi, lo]);
+
+        match self {
+            Register16::AF => pair(registers.a, registers.f),
+            Register16::BC => pair(registers.b, registers.c),
+            Register16::DE => pair(registers.d, registers.e),
+            Register16::HL => pair(registers.h, registers.l),
+            Register16::SP => registers.sp,
+            Register16::PC => registers.pc,
+        }
+    }
+
+    fn write(self, registers: &mut Registers, value: u16) {
+        let [hi, lo] = value.to_be_bytes();
+
+        match self {
+            Register16::AF => {
+                registers.a = hi;
+                registers.f = lo & 0xF0;
+            }
+            Register16::BC => {
+                registers.b = hi;
+                registers.c = lo;
+            }
+            Register16::DE => {
+                registers.d = hi;
+                registers.e = lo;
+            }
+            Register16::HL => {
+                registers.h = hi;
+                registers.l = lo;
+            }
+            Register16::SP => registers.sp = value,
+            Register16::PC => registers.pc = value,
+        }
+    }
+
+    fn inc(self, registers: &mut Registers) {
+        let value = self.fetch(registers).wrapping_add(1);
+        self.write(registers, value);
+    }
+
+    fn dec(self, registers: &mut Registers) {
+        let value = self.fetch(registers).wrapping_sub(1);
+        self.write(registers, value);
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+
+/// Ring buffer of save states captured every `interval` frames
+#[derive(Debug, Clone)]
+pub struct RewindBuffer {
+    states: VecDeque<Vec<u8>>,
+    capacity: usize,
+    interval: u32,
+    frames_since_capture: u32,
+}
+
+impl RewindBuffer {
+    /// Keep up to `capacity` states, one every `interval` frames
+    pub fn new(capacity: usize, interval: u32) -> Self {
+        Self {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+            frames_since_capture: 0,
+        }
+    }
+
+    /// Note that a frame has finished, returning whether a state should be
+    /// captured now
+    pub fn frame_finished(&mut self) -> bool {
+        self.frames_since_capture += 1;
+        if self.frames_since_capture < self.interval {
+            return false;
+        }
+
+        self.frames_since_capture = 0;
+        true
+    }
+
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(state);
+    }
+
+    /// Take the most recently captured state
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.frames_since_capture = 0;
+        self.states.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
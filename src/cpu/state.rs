@@ -0,0 +1,299 @@
+use anyhow::{bail, Result};
+use thiserror::Error;
+
+use crate::cartridge::CartridgeError;
+use crate::joypad::Joypad;
+use crate::memory::{Address, MEMORY_SIZE};
+use crate::ppu::{self, Ppu};
+use crate::register;
+use crate::timer::Timer;
+
+use super::registers::Registers;
+use super::Cpu;
+
+/// Identifies a save state blob
+pub const MAGIC: [u8; 4] = *b"GBST";
+
+/// Bumped whenever the layout of a save state changes
+pub const VERSION: u8 = 4;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StateError {
+    #[error("not a save state")]
+    BadMagic,
+    #[error("save state version {0} is not supported, expected {VERSION}")]
+    UnsupportedVersion(u8),
+    #[error("save state is truncated")]
+    Truncated,
+    #[error("save state has {0} unexpected trailing bytes")]
+    TrailingBytes(usize),
+    #[error("save state memory is {0} bytes, expected {MEMORY_SIZE}")]
+    MemorySize(usize),
+    #[error("save state cartridge does not match the inserted cartridge")]
+    CartridgeMismatch,
+}
+
+/// Banking state and external RAM of the inserted cartridge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeState {
+    pub ram: Vec<u8>,
+    pub mbc: Vec<u8>,
+}
+
+/// Everything needed to resume execution from a point in time
+///
+/// Cartridge ROM is not included, a state can only be restored into a CPU
+/// with the same cartridge inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuState {
+    pub registers: Registers,
+    pub halted: bool,
+    pub halt_bug: bool,
+    pub stopped: bool,
+    pub ime: bool,
+    pub ime_pending: bool,
+    pub cycles: u64,
+    pub memory: Vec<u8>,
+    pub joypad: Joypad,
+    pub timer: Timer,
+    pub ppu: Ppu,
+    pub cartridge: Option<CartridgeState>,
+}
+
+impl CpuState {
+    /// Serialize as `MAGIC`, `VERSION`, then the register, CPU flag,
+    /// memory, joypad, timer, PPU and cartridge sections
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MEMORY_SIZE + 64);
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+
+        let r = &self.registers;
+        out.extend_from_slice(&[
+            r.fetch(register!(A)),
+            r.fetch(register!(F)),
+            r.fetch(register!(B)),
+            r.fetch(register!(C)),
+            r.fetch(register!(D)),
+            r.fetch(register!(E)),
+            r.fetch(register!(H)),
+            r.fetch(register!(L)),
+        ]);
+        out.extend_from_slice(&r.fetch(register!(SP)).to_le_bytes());
+        out.extend_from_slice(&r.fetch(register!(PC)).to_le_bytes());
+
+        let flags = [
+            self.halted,
+            self.stopped,
+            self.ime,
+            self.ime_pending,
+            self.halt_bug,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0u8, |acc, (bit, &set)| acc | (set as u8) << bit);
+        out.push(flags);
+        out.extend_from_slice(&self.cycles.to_le_bytes());
+
+        out.extend_from_slice(&self.memory);
+        out.extend_from_slice(&self.joypad.to_bytes());
+        out.extend_from_slice(&self.timer.to_bytes());
+        out.extend_from_slice(&self.ppu.to_bytes());
+
+        match &self.cartridge {
+            None => out.push(0),
+            Some(cartridge) => {
+                out.push(1);
+                out.extend_from_slice(&(cartridge.ram.len() as u32).to_le_bytes());
+                out.extend_from_slice(&cartridge.ram);
+                out.push(cartridge.mbc.len() as u8);
+                out.extend_from_slice(&cartridge.mbc);
+            }
+        }
+
+        out
+    }
+
+    /// Parse a blob produced by [`CpuState::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, StateError> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let mut registers = Registers::default();
+        for reg in [
+            register!(A),
+            register!(F),
+            register!(B),
+            register!(C),
+            register!(D),
+            register!(E),
+            register!(H),
+            register!(L),
+        ] {
+            registers.write(reg, reader.byte()?);
+        }
+        registers.write(register!(SP), reader.word()?);
+        registers.write(register!(PC), reader.word()?);
+
+        let flags = reader.byte()?;
+        let cycles = u64::from_le_bytes(reader.array()?);
+        let memory = reader.take(MEMORY_SIZE)?.to_vec();
+        let joypad = Joypad::from_bytes(reader.array()?);
+        let timer = Timer::from_bytes(reader.array()?);
+        let ppu = Ppu::from_bytes(reader.take(ppu::STATE_LEN)?).ok_or(StateError::Truncated)?;
+
+        let cartridge = match reader.byte()? {
+            0 => None,
+            _ => {
+                let ram_len = u32::from_le_bytes(reader.array()?) as usize;
+                let ram = reader.take(ram_len)?.to_vec();
+                let mbc_len = reader.byte()? as usize;
+                let mbc = reader.take(mbc_len)?.to_vec();
+                Some(CartridgeState { ram, mbc })
+            }
+        };
+
+        if !reader.0.is_empty() {
+            return Err(StateError::TrailingBytes(reader.0.len()));
+        }
+
+        Ok(Self {
+            registers,
+            halted: flags & 1 != 0,
+            stopped: flags & 1 << 1 != 0,
+            ime: flags & 1 << 2 != 0,
+            ime_pending: flags & 1 << 3 != 0,
+            halt_bug: flags & 1 << 4 != 0,
+            cycles,
+            memory,
+            joypad,
+            timer,
+            ppu,
+            cartridge,
+        })
+    }
+}
+
+/// Cursor over an encoded state
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.0.len() < len {
+            return Err(StateError::Truncated);
+        }
+
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.take(N)?.try_into().expect("took exactly N bytes"))
+    }
+
+    fn byte(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+}
+
+impl Cpu {
+    /// Capture the current state
+    pub fn state(&self) -> CpuState {
+        let memory = self
+            .memory
+            .read(Address(0x0000), MEMORY_SIZE)
+            .expect("whole address space is readable")
+            .to_vec();
+
+        let cartridge = self.memory.cartridge().map(|cartridge| CartridgeState {
+            ram: cartridge.ram().to_vec(),
+            mbc: cartridge.mbc().save_state(),
+        });
+
+        CpuState {
+            registers: self.registers.clone(),
+            halted: self.halted,
+            halt_bug: self.halt_bug,
+            stopped: self.stopped,
+            ime: self.ime,
+            ime_pending: self.ime_pending,
+            cycles: self.cycles,
+            memory,
+            joypad: self.memory.joypad().clone(),
+            timer: self.memory.timer().clone(),
+            ppu: self.memory.ppu().clone(),
+            cartridge,
+        }
+    }
+
+    /// Restore a state captured by [`Cpu::state`]
+    ///
+    /// Nothing is modified unless the whole state can be applied.
+    pub fn set_state(&mut self, state: &CpuState) -> Result<()> {
+        // Checked up front as the cartridge is restored before memory
+        if state.memory.len() != MEMORY_SIZE {
+            bail!(StateError::MemorySize(state.memory.len()));
+        }
+
+        match (self.memory.cartridge_mut(), &state.cartridge) {
+            (None, None) => {}
+            (Some(cartridge), Some(saved)) => {
+                if cartridge.ram().len() != saved.ram.len() {
+                    bail!(StateError::CartridgeMismatch);
+                }
+
+                // The last step that can fail, controllers validate the whole
+                // state before changing anything
+                cartridge
+                    .mbc_mut()
+                    .load_state(&saved.mbc)
+                    .map_err(|_: CartridgeError| StateError::CartridgeMismatch)?;
+                cartridge
+                    .load_ram(&saved.ram)
+                    .expect("RAM length checked above");
+            }
+            _ => bail!(StateError::CartridgeMismatch),
+        }
+
+        self.memory
+            .write(Address(0x0000), &state.memory)
+            .expect("length checked above");
+        *self.memory.joypad_mut() = state.joypad.clone();
+        *self.memory.timer_mut() = state.timer.clone();
+        *self.memory.ppu_mut() = state.ppu.clone();
+
+        self.registers = state.registers.clone();
+        self.halted = state.halted;
+        self.halt_bug = state.halt_bug;
+        self.stopped = state.stopped;
+        self.ime = state.ime;
+        self.ime_pending = state.ime_pending;
+        self.cycles = state.cycles;
+
+        Ok(())
+    }
+
+    /// Serialize the current state into a versioned binary blob
+    pub fn save_state(&self) -> Vec<u8> {
+        self.state().encode()
+    }
+
+    /// Restore a blob produced by [`Cpu::save_state`]
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let state = CpuState::decode(bytes)?;
+        self.set_state(&state)
+    }
+}
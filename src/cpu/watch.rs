@@ -0,0 +1,32 @@
+use crate::memory::Address;
+
+/// Which bus accesses to an address a watchpoint fires on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    pub(crate) fn matches(self, write: bool) -> bool {
+        match self {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+/// A watched address being touched, recorded for the debugger to collect
+/// with [`Cpu::take_watch_hit`](super::Cpu::take_watch_hit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Start of the instruction that made the access
+    pub pc: Address,
+    pub addr: Address,
+    /// [`WatchKind::Read`] or [`WatchKind::Write`], whichever happened
+    pub kind: WatchKind,
+    /// The byte read or written
+    pub value: u8,
+}
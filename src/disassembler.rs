@@ -0,0 +1,120 @@
+use crate::cpu::instructions::{DecodeError, Instruction, CB_PREFIX};
+use crate::cpu::Cpu;
+use crate::memory::Address;
+
+/// Symbolic name for a well-known address: the RST and interrupt vectors
+/// and the cartridge entry point
+pub fn symbol_for(addr: Address) -> Option<&'static str> {
+    let symbol = match addr.0 {
+        0x0000 => "RST_00",
+        0x0008 => "RST_08",
+        0x0010 => "RST_10",
+        0x0018 => "RST_18",
+        0x0020 => "RST_20",
+        0x0028 => "RST_28",
+        0x0030 => "RST_30",
+        0x0038 => "RST_38",
+        0x0040 => "VBlank",
+        0x0048 => "STAT",
+        0x0050 => "Timer",
+        0x0058 => "Serial",
+        0x0060 => "Joypad",
+        0x0100 => "Entry",
+        _ => return None,
+    };
+
+    Some(symbol)
+}
+
+/// Decodes a raw byte slice into consecutive instructions without a `Cpu`
+///
+/// Yields `(offset, instruction, length)`. Bytes that don't decode yield an
+/// error and a length of one, except a CB prefix which always covers two
+/// bytes, so decoding carries on from the next byte. An instruction whose
+/// operands run past the end of the slice is yielded with its full length
+/// and ends the stream.
+pub struct InstructionStream<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> InstructionStream<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+}
+
+impl Iterator for InstructionStream<'_> {
+    type Item = (usize, Result<Instruction, DecodeError>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let opcode = *self.bytes.get(offset)?;
+
+        let (instruction, length) = if opcode == CB_PREFIX {
+            let instruction = match self.bytes.get(offset + 1) {
+                Some(&cb_opcode) => Instruction::decode_cb(cb_opcode),
+                None => Err(DecodeError::UnknownOpcode {
+                    opcode: CB_PREFIX,
+                    cb: false,
+                }),
+            };
+            (instruction, 2)
+        } else {
+            match Instruction::decode(opcode) {
+                Ok(instruction) => {
+                    let length = instruction.length() as usize;
+                    (Ok(instruction), length)
+                }
+                Err(err) => (Err(err), 1),
+            }
+        };
+
+        self.offset += length;
+        Some((offset, instruction, length))
+    }
+}
+
+impl Cpu {
+    /// Decode `count` consecutive instructions starting at `start`, with
+    /// their mnemonics and immediates
+    ///
+    /// Lines at well-known addresses are prefixed with their
+    /// [`symbol_for`] label, e.g. `VBlank: RETI`.
+    ///
+    /// Memory is read as the CPU would see it, without counting accesses.
+    /// Undecodable bytes are listed as `DB` with no instruction and skipped
+    /// one at a time, so the rest of the range still decodes.
+    pub fn disassemble_range(
+        &self,
+        start: Address,
+        count: usize,
+    ) -> Vec<(Address, Option<Instruction>, String)> {
+        let mut addr = start;
+
+        (0..count)
+            .map(|_| {
+                let bytes: [u8; 3] =
+                    std::array::from_fn(|i| self.memory().read_byte(addr + i as u16));
+                let (_, instruction, length) = InstructionStream::new(&bytes)
+                    .next()
+                    .expect("a non-empty slice yields an instruction");
+
+                let (instruction, text) = match instruction {
+                    Ok(instruction) => {
+                        let text = instruction.with_operands(&bytes[1..length]).to_string();
+                        (Some(instruction), text)
+                    }
+                    Err(_) => (None, format!("DB {:02X}H", bytes[0])),
+                };
+                let line = match symbol_for(addr) {
+                    Some(symbol) => (addr, instruction, format!("{symbol}: {text}")),
+                    None => (addr, instruction, text),
+                };
+
+                addr = addr + length as u16;
+                line
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::memory::Address;
+
+/// P1, button group select and the selected buttons' state
+pub const P1: Address = Address(0xFF00);
+
+/// P1 bit, when clear, selecting the direction keys
+const SELECT_DIRECTIONS: u8 = 1 << 4;
+/// P1 bit, when clear, selecting the action buttons
+const SELECT_ACTIONS: u8 = 1 << 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    /// The button's bit in the low nibble of P1
+    fn bit(self) -> u8 {
+        match self {
+            Button::Right | Button::A => 1 << 0,
+            Button::Left | Button::B => 1 << 1,
+            Button::Up | Button::Select => 1 << 2,
+            Button::Down | Button::Start => 1 << 3,
+        }
+    }
+
+    fn is_direction(self) -> bool {
+        matches!(
+            self,
+            Button::Right | Button::Left | Button::Up | Button::Down
+        )
+    }
+}
+
+/// The P1 register and which buttons are held
+///
+/// Everything in P1 is active low: a group is selected by clearing its bit,
+/// and a held button in a selected group reads as 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Joypad {
+    /// The select bits last written, 4 and 5
+    select: u8,
+    /// Held buttons, one bit each as laid out in P1
+    directions: u8,
+    actions: u8,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self {
+            select: SELECT_DIRECTIONS | SELECT_ACTIONS,
+            directions: 0,
+            actions: 0,
+        }
+    }
+}
+
+impl Joypad {
+    pub fn read(&self) -> u8 {
+        0xC0 | self.select | !self.held() & 0x0F
+    }
+
+    /// Select button groups, returning whether a line went low and so
+    /// requests the joypad interrupt
+    pub fn write(&mut self, value: u8) -> bool {
+        self.update(|joypad| joypad.select = value & (SELECT_DIRECTIONS | SELECT_ACTIONS))
+    }
+
+    /// Press or release `button`, returning whether a line went low and so
+    /// requests the joypad interrupt
+    pub fn set_button(&mut self, button: Button, pressed: bool) -> bool {
+        self.update(|joypad| {
+            let group = if button.is_direction() {
+                &mut joypad.directions
+            } else {
+                &mut joypad.actions
+            };
+
+            if pressed {
+                *group |= button.bit();
+            } else {
+                *group &= !button.bit();
+            }
+        })
+    }
+
+    /// Serialize the select bits, held buttons are host input and aren't
+    /// saved
+    pub fn to_bytes(&self) -> [u8; 1] {
+        [self.select]
+    }
+
+    /// Restore state saved by [`Joypad::to_bytes`], with no buttons held
+    pub fn from_bytes(bytes: [u8; 1]) -> Self {
+        let [select] = bytes;
+        Self {
+            select: select & (SELECT_DIRECTIONS | SELECT_ACTIONS),
+            ..Self::default()
+        }
+    }
+
+    /// Held buttons in the selected groups, set bits are held
+    fn held(&self) -> u8 {
+        let mut held = 0;
+        if self.select & SELECT_DIRECTIONS == 0 {
+            held |= self.directions;
+        }
+        if self.select & SELECT_ACTIONS == 0 {
+            held |= self.actions;
+        }
+        held
+    }
+
+    fn update(&mut self, change: impl FnOnce(&mut Self)) -> bool {
+        let before = self.held();
+        change(self);
+        self.held() & !before != 0
+    }
+}
@@ -0,0 +1,8 @@
+pub mod cartridge;
+pub mod cpu;
+pub mod disassembler;
+pub mod joypad;
+pub mod memory;
+pub mod ppu;
+pub mod serial;
+pub mod timer;
@@ -0,0 +1,269 @@
+use std::ops::{Add, RangeInclusive, Sub};
+
+use thiserror::Error;
+
+use crate::cartridge::Cartridge;
+use crate::cpu::interrupts::{Interrupt, INTERRUPT_FLAG};
+use crate::joypad::{Button, Joypad};
+use crate::ppu::{Ppu, VRAM_SIZE, VRAM_START};
+use crate::serial::{Serial, SB, SC, START_INTERNAL};
+use crate::timer::Timer;
+
+/// Size of the Game Boy address space, `0x0000`–`0xFFFF` inclusive
+pub const MEMORY_SIZE: usize = 0x10000;
+
+/// Size of the cartridge ROM region, `0x0000`–`0x7FFF`
+pub const ROM_SIZE: usize = 0x8000;
+
+/// A location in the 16-bit address space
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address(pub u16);
+
+impl Address {
+    /// The address `delta` bytes away, as used by relative jumps
+    pub fn offset(self, delta: i8) -> Address {
+        Address(self.0.wrapping_add_signed(delta as i16))
+    }
+}
+
+/// Wraps from `0xFFFF` round to `0x0000`
+impl Add<u16> for Address {
+    type Output = Address;
+
+    fn add(self, rhs: u16) -> Address {
+        Address(self.0.wrapping_add(rhs))
+    }
+}
+
+/// Wraps from `0x0000` round to `0xFFFF`
+impl Sub<u16> for Address {
+    type Output = Address;
+
+    fn sub(self, rhs: u16) -> Address {
+        Address(self.0.wrapping_sub(rhs))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReadError {
+    #[error("reading {len} bytes from {start:#06X} runs past the end of memory")]
+    MemoryOverflow { start: u16, len: usize },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WriteError {
+    #[error("writing {len} bytes to {start:#06X} runs past the end of memory")]
+    MemoryOverflow { start: u16, len: usize },
+}
+
+/// The 64KB address space
+///
+/// With a cartridge inserted, byte accesses to the ROM (`0x0000`–`0x7FFF`)
+/// and external RAM (`0xA000`–`0xBFFF`) regions are delegated to it; all
+/// other addresses, and every address when no cartridge is present, are
+/// backed by flat memory. The joypad, timer and LCD registers are always
+/// routed to their components, and starting a serial transfer hands SB to the
+/// link port.
+pub struct Memory {
+    data: Box<[u8; MEMORY_SIZE]>,
+    cartridge: Option<Cartridge>,
+    joypad: Joypad,
+    timer: Timer,
+    ppu: Ppu,
+    serial: Serial,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            data: Box::new([0; MEMORY_SIZE]),
+            cartridge: None,
+            joypad: Joypad::default(),
+            timer: Timer::default(),
+            ppu: Ppu::default(),
+            serial: Serial::default(),
+        }
+    }
+}
+
+impl Memory {
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_ref()
+    }
+
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    /// Press or release `button`, requesting the joypad interrupt if that
+    /// pulls a selected line low, which is also returned
+    pub fn set_button(&mut self, button: Button, pressed: bool) -> bool {
+        let requested = self.joypad.set_button(button, pressed);
+        if requested {
+            self.request_interrupt(Interrupt::Joypad);
+        }
+        requested
+    }
+
+    pub fn joypad(&self) -> &Joypad {
+        &self.joypad
+    }
+
+    pub fn joypad_mut(&mut self) -> &mut Joypad {
+        &mut self.joypad
+    }
+
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    pub fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+
+    pub fn serial(&self) -> &Serial {
+        &self.serial
+    }
+
+    /// Borrow `len` bytes of flat memory starting at `start`
+    ///
+    /// The range is validated before any slicing, so an out of range or
+    /// overflowing length is reported as an error rather than a panic.
+    pub fn read(&self, start: Address, len: usize) -> Result<&[u8], ReadError> {
+        let begin = start.0 as usize;
+        let end = begin
+            .checked_add(len)
+            .filter(|&end| end <= MEMORY_SIZE)
+            .ok_or(ReadError::MemoryOverflow {
+                start: start.0,
+                len,
+            })?;
+
+        Ok(&self.data[begin..end])
+    }
+
+    /// Copy `len` bytes of flat memory starting at `start` out into a new
+    /// buffer, so memory can be written while it's held
+    pub fn read_owned(&self, start: Address, len: usize) -> Result<Vec<u8>, ReadError> {
+        self.read(start, len).map(<[u8]>::to_vec)
+    }
+
+    /// Copy `bytes` into flat memory starting at `start`
+    pub fn write(&mut self, start: Address, bytes: &[u8]) -> Result<(), WriteError> {
+        let begin = start.0 as usize;
+        let end = begin
+            .checked_add(bytes.len())
+            .filter(|&end| end <= MEMORY_SIZE)
+            .ok_or(WriteError::MemoryOverflow {
+                start: start.0,
+                len: bytes.len(),
+            })?;
+
+        self.data[begin..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn read_byte(&self, addr: Address) -> u8 {
+        match (&self.cartridge, addr.0) {
+            (Some(cartridge), 0x0000..=0x7FFF | 0xA000..=0xBFFF) => cartridge.read(addr),
+            (_, 0xFF00) => self.joypad.read(),
+            (_, 0xFF04..=0xFF07) => self.timer.read(addr),
+            (_, 0xFF40..=0xFF45 | 0xFF47) => self.ppu.read(addr),
+            _ => self.data[addr.0 as usize],
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: Address, value: u8) {
+        match (&mut self.cartridge, addr.0) {
+            (Some(cartridge), 0x0000..=0x7FFF | 0xA000..=0xBFFF) => cartridge.write(addr, value),
+            (_, 0xFF00) => {
+                if self.joypad.write(value) {
+                    self.request_interrupt(Interrupt::Joypad);
+                }
+            }
+            (_, 0xFF04..=0xFF07) => self.timer.write(addr, value),
+            (_, 0xFF40..=0xFF45 | 0xFF47) => self.ppu.write(addr, value),
+            (_, 0xFF02) if value == START_INTERNAL => {
+                // The transfer finishes straight away, so the start bit is
+                // already clear and the serial interrupt requested
+                let received = self.serial.transfer(self.data[SB.0 as usize]);
+                self.data[SB.0 as usize] = received;
+                self.data[SC.0 as usize] = value & 0x7F;
+                self.request_interrupt(Interrupt::Serial);
+            }
+            _ => self.data[addr.0 as usize] = value,
+        }
+    }
+
+    /// Copy `bytes` into flat memory starting at `start`, see [`Memory::write`]
+    pub fn load_at(&mut self, start: Address, bytes: &[u8]) -> Result<(), WriteError> {
+        self.write(start, bytes)
+    }
+
+    /// Set every byte of flat memory in `range` to `value`
+    pub fn fill(&mut self, range: RangeInclusive<Address>, value: u8) {
+        let (start, end) = range.into_inner();
+        if start <= end {
+            self.data[start.0 as usize..=end.0 as usize].fill(value);
+        }
+    }
+
+    /// Read a little-endian word, wrapping from `0xFFFF` round to `0x0000`
+    pub fn read_word(&self, addr: Address) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr + 1)])
+    }
+
+    /// Write a little-endian word, wrapping from `0xFFFF` round to `0x0000`
+    pub fn write_word(&mut self, addr: Address, value: u16) {
+        let [lo, hi] = value.to_le_bytes();
+        self.write_byte(addr, lo);
+        self.write_byte(addr + 1, hi);
+    }
+
+    /// Set `interrupt`'s bit in IF, leaving other requests alone
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.data[INTERRUPT_FLAG.0 as usize] |= interrupt.bit();
+    }
+
+    /// Advance the hardware clocked alongside the CPU by `cycles` machine
+    /// cycles, raising any interrupts they request
+    pub fn tick(&mut self, cycles: u64) {
+        if self.timer.tick(cycles) {
+            self.request_interrupt(Interrupt::Timer);
+        }
+
+        let vram = self.data[VRAM_START..][..VRAM_SIZE]
+            .try_into()
+            .expect("VRAM lies within memory");
+        let requests = self.ppu.tick(cycles, vram);
+        for interrupt in Interrupt::ALL {
+            if requests & interrupt.bit() != 0 {
+                self.request_interrupt(interrupt);
+            }
+        }
+    }
+
+    /// Machine cycles until the next interrupt the clocked hardware will
+    /// request on its own, if any
+    pub fn cycles_until_event(&self) -> Option<u64> {
+        [
+            self.timer.cycles_until_overflow(),
+            self.ppu.cycles_until_mode_change(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+}
@@ -0,0 +1,282 @@
+pub mod stat;
+
+use crate::cpu::interrupts::Interrupt;
+use crate::memory::Address;
+
+use stat::StatLine;
+
+pub const LCDC: Address = Address(0xFF40);
+pub const STAT: Address = Address(0xFF41);
+pub const SCY: Address = Address(0xFF42);
+pub const SCX: Address = Address(0xFF43);
+pub const LY: Address = Address(0xFF44);
+pub const LYC: Address = Address(0xFF45);
+pub const BGP: Address = Address(0xFF47);
+
+/// Start of video RAM, tile data and the two tile maps
+pub const VRAM_START: usize = 0x8000;
+pub const VRAM_SIZE: usize = 0x2000;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+/// LCDC bit turning the display and PPU on
+pub const LCD_ENABLE: u8 = 1 << 7;
+/// LCDC bit selecting tile data at `0x8000` with unsigned indices, rather
+/// than `0x8800` with signed ones
+pub const TILE_DATA_SELECT: u8 = 1 << 4;
+/// LCDC bit selecting the background tile map at `0x9C00` over `0x9800`
+pub const BG_MAP_SELECT: u8 = 1 << 3;
+/// LCDC bit enabling the background, blank when clear
+pub const BG_ENABLE: u8 = 1 << 0;
+
+/// Dots (clocks) per scanline, including HBlank
+pub const LINE_DOTS: u16 = 456;
+/// Scanlines per frame, the last 10 of which are VBlank
+pub const LINES: u8 = 154;
+/// Visible scanlines, VBlank starts on the line after
+pub const VISIBLE_LINES: u8 = 144;
+
+const OAM_SEARCH_DOTS: u16 = 80;
+const PIXEL_TRANSFER_DOTS: u16 = 172;
+/// Length of [`Ppu::to_bytes`]
+pub const STATE_LEN: usize = 10 + SCREEN_WIDTH * SCREEN_HEIGHT;
+
+const FRAME_DOTS: u64 = LINE_DOTS as u64 * LINES as u64;
+
+/// The PPU mode, as reported in the low two bits of STAT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamSearch = 2,
+    PixelTransfer = 3,
+}
+
+/// LCD registers, the scanline timing state machine and the framebuffer
+///
+/// Each visible line spends 80 dots in OAM search, 172 in pixel transfer and
+/// the rest of its 456 in HBlank, followed by 10 lines of VBlank. A line's
+/// background is drawn as its pixel transfer ends. With the LCD off the PPU
+/// sits at LY 0 in HBlank.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ppu {
+    lcdc: u8,
+    /// Only the interrupt source enables, bits 3-6
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    /// Position within the current line
+    dot: u16,
+    stat_line: StatLine,
+    /// Shades 0 (lightest) to 3, row by row
+    framebuffer: Box<[u8; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self {
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            dot: 0,
+            stat_line: StatLine::default(),
+            framebuffer: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+        }
+    }
+}
+
+impl Ppu {
+    pub fn read(&self, addr: Address) -> u8 {
+        match addr {
+            LCDC => self.lcdc,
+            STAT => {
+                let coincidence = (self.ly == self.lyc) as u8;
+                0x80 | self.stat | coincidence << 2 | self.mode() as u8
+            }
+            SCY => self.scy,
+            SCX => self.scx,
+            LY => self.ly,
+            LYC => self.lyc,
+            BGP => self.bgp,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, addr: Address, value: u8) {
+        match addr {
+            LCDC => {
+                if value & LCD_ENABLE == 0 {
+                    self.ly = 0;
+                    self.dot = 0;
+                }
+                self.lcdc = value;
+            }
+            STAT => self.stat = value & 0x78,
+            SCY => self.scy = value,
+            SCX => self.scx = value,
+            LYC => self.lyc = value,
+            BGP => self.bgp = value,
+            _ => {}
+        }
+    }
+
+    pub fn framebuffer(&self) -> &[u8; SCREEN_WIDTH * SCREEN_HEIGHT] {
+        &self.framebuffer
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lcdc & LCD_ENABLE != 0
+    }
+
+    pub fn mode(&self) -> Mode {
+        if !self.is_enabled() {
+            Mode::HBlank
+        } else if self.ly >= VISIBLE_LINES {
+            Mode::VBlank
+        } else if self.dot < OAM_SEARCH_DOTS {
+            Mode::OamSearch
+        } else if self.dot < OAM_SEARCH_DOTS + PIXEL_TRANSFER_DOTS {
+            Mode::PixelTransfer
+        } else {
+            Mode::HBlank
+        }
+    }
+
+    /// Advance by `cycles` machine cycles, drawing from `vram`, returning the
+    /// IF bits to request
+    pub fn tick(&mut self, cycles: u64, vram: &[u8; VRAM_SIZE]) -> u8 {
+        if !self.is_enabled() {
+            return 0;
+        }
+
+        // Every frame after the first ends where it started and requests
+        // the same interrupts again, so only one needs running
+        let mut dots = cycles * 4;
+        if dots > 2 * FRAME_DOTS {
+            dots = FRAME_DOTS + dots % FRAME_DOTS;
+        }
+
+        let mut requests = 0;
+        while dots > 0 {
+            let step = dots.min(self.dots_until_mode_change() as u64);
+            let before = self.mode();
+            self.dot += step as u16;
+            if before == Mode::PixelTransfer && self.mode() == Mode::HBlank {
+                self.render_line(vram);
+            }
+            dots -= step;
+
+            if self.dot == LINE_DOTS {
+                self.dot = 0;
+                self.ly = (self.ly + 1) % LINES;
+                if self.ly == VISIBLE_LINES {
+                    requests |= Interrupt::VBlank.bit();
+                }
+            }
+
+            if self
+                .stat_line
+                .update(self.stat, self.mode(), self.ly, self.lyc)
+            {
+                requests |= Interrupt::Stat.bit();
+            }
+        }
+
+        requests
+    }
+
+    /// Machine cycles until the mode or LY next changes, `None` while the
+    /// LCD is off
+    pub fn cycles_until_mode_change(&self) -> Option<u64> {
+        self.is_enabled()
+            .then(|| (self.dots_until_mode_change() as u64).div_ceil(4))
+    }
+
+    /// Draw the background for line LY into the framebuffer
+    fn render_line(&mut self, vram: &[u8; VRAM_SIZE]) {
+        let row = &mut self.framebuffer[self.ly as usize * SCREEN_WIDTH..][..SCREEN_WIDTH];
+        if self.lcdc & BG_ENABLE == 0 {
+            row.fill(0);
+            return;
+        }
+
+        let map = if self.lcdc & BG_MAP_SELECT != 0 {
+            0x1C00
+        } else {
+            0x1800
+        };
+        let y = self.ly.wrapping_add(self.scy);
+
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let x = (x as u8).wrapping_add(self.scx);
+            let index = vram[map + (y / 8) as usize * 32 + (x / 8) as usize];
+
+            let tile = if self.lcdc & TILE_DATA_SELECT != 0 {
+                index as usize * 16
+            } else {
+                (0x1000 + index as i8 as isize * 16) as usize
+            };
+            let lo = vram[tile + (y % 8) as usize * 2];
+            let hi = vram[tile + (y % 8) as usize * 2 + 1];
+
+            let bit = 7 - x % 8;
+            let color = (hi >> bit & 1) << 1 | lo >> bit & 1;
+            *pixel = self.bgp >> (color * 2) & 0b11;
+        }
+    }
+
+    fn dots_until_mode_change(&self) -> u16 {
+        match self.mode() {
+            Mode::OamSearch => OAM_SEARCH_DOTS - self.dot,
+            Mode::PixelTransfer => OAM_SEARCH_DOTS + PIXEL_TRANSFER_DOTS - self.dot,
+            Mode::HBlank | Mode::VBlank => LINE_DOTS - self.dot,
+        }
+    }
+
+    /// Internal state, for save states
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let [lo, hi] = self.dot.to_le_bytes();
+        let mut out = vec![
+            self.lcdc,
+            self.stat,
+            self.scy,
+            self.scx,
+            self.ly,
+            self.lyc,
+            self.bgp,
+            lo,
+            hi,
+            self.stat_line.is_high() as u8,
+        ];
+        out.extend_from_slice(&self.framebuffer[..]);
+        out
+    }
+
+    /// Restore state saved by [`Ppu::to_bytes`], `None` if `bytes` is the
+    /// wrong length
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&[lcdc, stat, scy, scx, ly, lyc, bgp, lo, hi, line], framebuffer) =
+            bytes.split_first_chunk::<10>()?;
+
+        Some(Self {
+            lcdc,
+            stat: stat & 0x78,
+            scy,
+            scx,
+            ly: ly % LINES,
+            lyc,
+            bgp,
+            dot: u16::from_le_bytes([lo, hi]) % LINE_DOTS,
+            stat_line: StatLine::with_level(line != 0),
+            framebuffer: Box::new(framebuffer.try_into().ok()?),
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use super::Mode;
+
+/// STAT bit enabling the HBlank (mode 0) source
+pub const HBLANK_SOURCE: u8 = 1 << 3;
+/// STAT bit enabling the VBlank (mode 1) source
+pub const VBLANK_SOURCE: u8 = 1 << 4;
+/// STAT bit enabling the OAM search (mode 2) source
+pub const OAM_SOURCE: u8 = 1 << 5;
+/// STAT bit enabling the LY=LYC coincidence source
+pub const LYC_SOURCE: u8 = 1 << 6;
+
+/// The internal STAT interrupt line
+///
+/// All enabled sources are ORed onto a single line and the interrupt is only
+/// requested on its rising edge. While any source holds the line high, other
+/// sources becoming active do not request a second interrupt ("STAT
+/// blocking").
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatLine {
+    high: bool,
+}
+
+impl StatLine {
+    /// A line already at the given level, for restoring saved state
+    pub fn with_level(high: bool) -> Self {
+        Self { high }
+    }
+
+    /// Re-evaluate the line from STAT's enable bits and the current PPU
+    /// state, returning whether a STAT interrupt should be requested
+    pub fn update(&mut self, stat: u8, mode: Mode, ly: u8, lyc: u8) -> bool {
+        let high = (stat & HBLANK_SOURCE != 0 && mode == Mode::HBlank)
+            || (stat & VBLANK_SOURCE != 0 && mode == Mode::VBlank)
+            || (stat & OAM_SOURCE != 0 && mode == Mode::OamSearch)
+            || (stat & LYC_SOURCE != 0 && ly == lyc);
+
+        let rising = high && !self.high;
+        self.high = high;
+        rising
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+}
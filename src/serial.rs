@@ -0,0 +1,33 @@
+use crate::memory::Address;
+
+/// SB, the byte to send and, once a transfer finishes, the byte received
+pub const SB: Address = Address(0xFF01);
+
+/// SC, bit 7 starts a transfer and bit 0 selects the internal clock
+pub const SC: Address = Address(0xFF02);
+
+/// Writing this to SC sends SB using the internal clock
+pub const START_INTERNAL: u8 = 0x81;
+
+/// The link port, with nothing plugged in
+///
+/// Transfers complete immediately. Every byte sent is kept, since test ROMs
+/// print their results this way.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Serial {
+    output: String,
+}
+
+impl Serial {
+    /// Send `byte`, returning the byte shifted in, which is always `0xFF`
+    /// without a link partner
+    pub fn transfer(&mut self, byte: u8) -> u8 {
+        self.output.push(byte as char);
+        0xFF
+    }
+
+    /// Everything sent so far
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+}
@@ -0,0 +1,107 @@
+use crate::memory::Address;
+
+pub const DIV: Address = Address(0xFF04);
+pub const TIMA: Address = Address(0xFF05);
+pub const TMA: Address = Address(0xFF06);
+pub const TAC: Address = Address(0xFF07);
+
+/// DIV/TIMA/TMA/TAC
+///
+/// DIV is the upper byte of a 16-bit counter incremented every clock
+/// (4 per machine cycle). TIMA counts falling edges of the counter bit
+/// selected by TAC, and reloads from TMA when it overflows.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Timer {
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+}
+
+impl Timer {
+    pub fn read(&self, addr: Address) -> u8 {
+        match addr {
+            DIV => (self.counter >> 8) as u8,
+            TIMA => self.tima,
+            TMA => self.tma,
+            TAC => self.tac | 0xF8,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, addr: Address, value: u8) {
+        match addr {
+            DIV => self.counter = 0,
+            TIMA => self.tima = value,
+            TMA => self.tma = value,
+            TAC => self.tac = value & 0b111,
+            _ => {}
+        }
+    }
+
+    /// Internal state, for save states
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let [lo, hi] = self.counter.to_le_bytes();
+        [lo, hi, self.tima, self.tma, self.tac]
+    }
+
+    /// Restore state saved by [`Timer::to_bytes`]
+    pub fn from_bytes(bytes: [u8; 5]) -> Self {
+        let [lo, hi, tima, tma, tac] = bytes;
+        Self {
+            counter: u16::from_le_bytes([lo, hi]),
+            tima,
+            tma,
+            tac: tac & 0b111,
+        }
+    }
+
+    /// Clocks between TIMA increments, `None` while TIMA is stopped
+    fn period(&self) -> Option<u64> {
+        if self.tac & 0b100 == 0 {
+            return None;
+        }
+
+        Some(match self.tac & 0b11 {
+            0b00 => 1024,
+            0b01 => 16,
+            0b10 => 64,
+            _ => 256,
+        })
+    }
+
+    /// Advance by `cycles` machine cycles, returning whether TIMA overflowed
+    pub fn tick(&mut self, cycles: u64) -> bool {
+        let old = self.counter as u64;
+        let new = old + cycles * 4;
+        self.counter = new as u16;
+
+        let Some(period) = self.period() else {
+            return false;
+        };
+
+        let increments = new / period - old / period;
+        let to_overflow = 0x100 - self.tima as u64;
+
+        if increments < to_overflow {
+            self.tima += increments as u8;
+            return false;
+        }
+
+        // Each overflow reloads TMA, so the rest wraps within TMA..=0xFF
+        let remaining = increments - to_overflow;
+        let reload_span = 0x100 - self.tma as u64;
+        self.tima = self.tma + (remaining % reload_span) as u8;
+        true
+    }
+
+    /// Machine cycles until TIMA next overflows, `None` while it is stopped
+    pub fn cycles_until_overflow(&self) -> Option<u64> {
+        let period = self.period()?;
+        let counter = self.counter as u64;
+
+        let to_next_increment = period - counter % period;
+        let clocks = to_next_increment + (0xFF - self.tima as u64) * period;
+        Some(clocks.div_ceil(4))
+    }
+}
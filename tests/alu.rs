@@ -0,0 +1,423 @@
+use gaemboi::cpu::instructions::{ArithOp, Instruction, InstructionType, Operand, RotateOp};
+use gaemboi::cpu::registers::Flag;
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+/// Run `<op> A,B` with A and B preset and an optional incoming carry, then
+/// check A and every flag
+macro_rules! alu_test {
+    ($name:ident: $op:ident, a=$a:expr, op=$b:expr $(, carry=$cin:expr)?
+        => result=$result:expr, z=$z:expr, n=$n:expr, h=$h:expr, c=$c:expr) => {
+        #[test]
+        fn $name() {
+            let opcode = 0x80 | ((ArithOp::$op as u8) << 3);
+
+            let mut cpu = Cpu::default();
+            cpu.load_rom_with_entry(&[opcode], Address(0x0000)).unwrap();
+            cpu.registers_mut().write(register!(A), $a);
+            cpu.registers_mut().write(register!(B), $b);
+            $(cpu.registers_mut().set_flag(Flag::Carry, $cin);)?
+
+            cpu.step().unwrap();
+
+            assert_eq!(cpu.registers().fetch(register!(A)), $result, "A");
+            assert_eq!(cpu.registers().flag(Flag::Zero), $z, "Z");
+            assert_eq!(cpu.registers().flag(Flag::Sub), $n, "N");
+            assert_eq!(cpu.registers().flag(Flag::HalfCarry), $h, "H");
+            assert_eq!(cpu.registers().flag(Flag::Carry), $c, "C");
+        }
+    };
+}
+
+alu_test!(add_half_carry: Add, a=0x0F, op=0x01 => result=0x10, z=false, n=false, h=true, c=false);
+alu_test!(add_carry_and_zero: Add, a=0x80, op=0x80 => result=0x00, z=true, n=false, h=false, c=true);
+alu_test!(add_wraps: Add, a=0xFF, op=0x01 => result=0x00, z=true, n=false, h=true, c=true);
+alu_test!(adc_carry_in_half_carry: Adc, a=0x0E, op=0x01, carry=true => result=0x10, z=false, n=false, h=true, c=false);
+alu_test!(adc_carry_in_full_carry: Adc, a=0xFE, op=0x01, carry=true => result=0x00, z=true, n=false, h=true, c=true);
+alu_test!(sub_half_borrow: Sub, a=0x10, op=0x01 => result=0x0F, z=false, n=true, h=true, c=false);
+alu_test!(sub_borrow: Sub, a=0x00, op=0x01 => result=0xFF, z=false, n=true, h=true, c=true);
+alu_test!(sub_equal_is_zero: Sub, a=0x42, op=0x42 => result=0x00, z=true, n=true, h=false, c=false);
+alu_test!(sbc_carry_in_borrows: Sbc, a=0x10, op=0x0F, carry=true => result=0x00, z=true, n=true, h=true, c=false);
+alu_test!(sbc_carry_in_full_borrow: Sbc, a=0x00, op=0xFF, carry=true => result=0x00, z=true, n=true, h=true, c=true);
+alu_test!(and_sets_half_carry: And, a=0x0F, op=0xF0 => result=0x00, z=true, n=false, h=true, c=false);
+alu_test!(xor_clears_carry: Xor, a=0xFF, op=0x0F, carry=true => result=0xF0, z=false, n=false, h=false, c=false);
+alu_test!(or_zero: Or, a=0x00, op=0x00 => result=0x00, z=true, n=false, h=false, c=false);
+alu_test!(and_clears_carry: And, a=0xF3, op=0x3C, carry=true => result=0x30, z=false, n=false, h=true, c=false);
+alu_test!(or_clears_carry: Or, a=0x50, op=0x0A, carry=true => result=0x5A, z=false, n=false, h=false, c=false);
+alu_test!(cp_keeps_a: Cp, a=0x3C, op=0x2F => result=0x3C, z=false, n=true, h=true, c=false);
+
+#[test]
+fn arithmetic_through_hl_wraps() {
+    // ADD A,(HL); ADC A,(HL); SUB (HL); SBC A,(HL)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x86, 0x8E, 0x96, 0x9E], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.memory_mut().write_byte(Address(0xC000), 0x01);
+    cpu.registers_mut().write(register!(A), 0xFF);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x00);
+    assert!(cpu.registers().flag(Flag::Carry));
+
+    // Carry from the ADD feeds the ADC
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x02);
+    assert!(!cpu.registers().flag(Flag::Carry));
+
+    cpu.registers_mut().write(register!(A), 0x00);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0xFF);
+    assert!(cpu.registers().flag(Flag::Carry));
+
+    // Borrow from the SUB feeds the SBC
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0xFD);
+}
+
+#[test]
+fn add_recomputes_every_flag() {
+    // ADD A,B with every flag set beforehand
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x80], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(F), 0xF0);
+    cpu.registers_mut().write(register!(A), 0x01);
+    cpu.registers_mut().write(register!(B), 0x01);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x02);
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x00);
+}
+
+#[test]
+fn register_block_decodes_to_arith8() {
+    let cases = [
+        (0x80, ArithOp::Add, Operand::Register8(register!(B)), 1),
+        (0xAF, ArithOp::Xor, Operand::Register8(register!(A)), 1),
+        (0xBE, ArithOp::Cp, Operand::Indirect(register!(HL)), 2),
+    ];
+
+    for (opcode, op, src, cycles) in cases {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(
+            *instruction.itype(),
+            InstructionType::Arith8 { op, src },
+            "opcode {opcode:#04X}"
+        );
+        assert_eq!(instruction.cycles(), cycles, "opcode {opcode:#04X}");
+    }
+}
+
+#[test]
+fn cp_immediate_sets_flags_only() {
+    let instruction = Instruction::decode(0xFE).unwrap();
+    assert_eq!(
+        *instruction.itype(),
+        InstructionType::Arith8 {
+            op: ArithOp::Cp,
+            src: Operand::Immediate8
+        }
+    );
+    assert_eq!(instruction.cycles(), 2);
+    assert_eq!(instruction.length(), 2);
+
+    // CP 0x42 against an equal A
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xFE, 0x42], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), 0x42);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x42);
+    assert_eq!(cpu.registers().fetch(register!(PC)), 0x0002);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(cpu.registers().flag(Flag::Sub));
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn immediate_block_shares_op_order() {
+    // ADD A,d8; SUB d8; AND d8
+    for (opcode, op) in [
+        (0xC6, ArithOp::Add),
+        (0xD6, ArithOp::Sub),
+        (0xE6, ArithOp::And),
+    ] {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(
+            *instruction.itype(),
+            InstructionType::Arith8 {
+                op,
+                src: Operand::Immediate8
+            },
+            "opcode {opcode:#04X}"
+        );
+    }
+}
+
+#[test]
+fn inc_wraps_to_zero_and_keeps_carry() {
+    // INC B
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x04], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(B), 0xFF);
+    cpu.registers_mut().set_flag(Flag::Sub, true);
+    cpu.registers_mut().set_flag(Flag::Carry, true);
+
+    assert_eq!(cpu.step().unwrap(), 1);
+
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x00);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn dec_wraps_to_ff_with_half_borrow() {
+    // DEC (HL)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x35], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0xFF);
+    assert!(!cpu.registers().flag(Flag::Zero));
+    assert!(cpu.registers().flag(Flag::Sub));
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn inc_dec_pairs_leave_flags_alone() {
+    // INC BC; DEC SP
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x03, 0x3B], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(BC), 0xFFFF);
+    cpu.registers_mut().write(register!(SP), 0x0000);
+    cpu.registers_mut().write(register!(F), 0x50);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(BC)), 0x0000);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(SP)), 0xFFFF);
+
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x50);
+}
+
+#[test]
+fn add_sp_positive_crosses_byte() {
+    // ADD SP,+0x10
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xE8, 0x10], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xC0F8);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+
+    assert_eq!(cpu.sp(), 0xC108);
+    assert!(!cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn add_sp_negative_sets_flags_from_unsigned_bytes() {
+    // ADD SP,-2
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xE8, 0xFE], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    cpu.step().unwrap();
+
+    // 0xFE + 0xFE as bytes carries out of both nibble and byte
+    assert_eq!(cpu.sp(), 0xFFFC);
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn add_hl_carries_from_bits_11_and_15() {
+    // ADD HL,BC
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x09], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), 0x8A23);
+    cpu.registers_mut().write(register!(BC), 0x0605);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+    cpu.registers_mut().set_flag(Flag::Sub, true);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+
+    assert_eq!(cpu.registers().fetch(register!(HL)), 0x9028);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn add_hl_hl_carries_out() {
+    // ADD HL,HL
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x29], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), 0x8A23);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(HL)), 0x1446);
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::Zero));
+}
+
+/// Run `op A,B` then DAA, returning the CPU
+fn bcd(opcode: u8, a: u8, b: u8) -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[opcode, 0x27], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), a);
+    cpu.registers_mut().write(register!(B), b);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu
+}
+
+#[test]
+fn daa_after_add() {
+    // ADD A,B
+    // The binary sum is 0x11 with a half carry, corrected to BCD 17
+    let cpu = bcd(0x80, 0x09, 0x08);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x17);
+    assert!(!cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+
+    let cpu = bcd(0x80, 0x45, 0x55);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x00);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn daa_after_sub() {
+    // SUB B
+    let cpu = bcd(0x90, 0x10, 0x01);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x09);
+    assert!(cpu.registers().flag(Flag::Sub));
+    assert!(!cpu.registers().flag(Flag::Carry));
+
+    let cpu = bcd(0x90, 0x00, 0x01);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x99);
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn rlca_clears_zero() {
+    // RLCA
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x07], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(A), 0x85);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+
+    assert_eq!(cpu.step().unwrap(), 1);
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x0B);
+    assert!(cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::Zero));
+}
+
+#[test]
+fn rla_to_zero_still_clears_zero() {
+    // RLA
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x17], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(A), 0x80);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x00);
+    assert!(cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::Zero));
+}
+
+#[test]
+fn rrca_and_rra_decode() {
+    assert_eq!(
+        *Instruction::decode(0x0F).unwrap().itype(),
+        InstructionType::RotateA(RotateOp::Rrc)
+    );
+    assert_eq!(
+        *Instruction::decode(0x1F).unwrap().itype(),
+        InstructionType::RotateA(RotateOp::Rr)
+    );
+}
+
+#[test]
+fn cpl_scf_ccf() {
+    let mut cpu = Cpu::default();
+    // CPL; SCF; CCF
+    cpu.load_rom_with_entry(&[0x2F, 0x37, 0x3F], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), 0x35);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0xCA);
+    assert_eq!(cpu.registers().fetch(register!(F)), 0xE0);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x90);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x80);
+}
+
+#[test]
+fn xor_a_a_zeroes_and_clears_half_carry() {
+    let mut cpu = Cpu::default();
+    // XOR A
+    cpu.load_rom_with_entry(&[0xAF], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(A), 0x9C);
+    cpu.registers_mut().write(register!(F), 0x70);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x00);
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x80);
+}
+
+#[test]
+fn cp_flags_match_sub_without_writing_a() {
+    let run = |opcode: u8, a: u8, b: u8| {
+        let mut cpu = Cpu::default();
+        cpu.load_rom_with_entry(&[opcode], Address(0x0000)).unwrap();
+        cpu.registers_mut().write(register!(A), a);
+        cpu.registers_mut().write(register!(B), b);
+        cpu.step().unwrap();
+        (
+            cpu.registers().fetch(register!(A)),
+            cpu.registers().fetch(register!(F)),
+        )
+    };
+
+    for a in [0x00, 0x01, 0x10, 0x3C, 0x80, 0xFF] {
+        for b in [0x00, 0x01, 0x0F, 0x2F, 0x80, 0xFF] {
+            // SUB B; CP B
+            let (_, sub_flags) = run(0x90, a, b);
+            let (cp_a, cp_flags) = run(0xB8, a, b);
+
+            assert_eq!(cp_a, a, "CP {a:#04X},{b:#04X} wrote A");
+            assert_eq!(cp_flags, sub_flags, "CP {a:#04X},{b:#04X}");
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use gaemboi::cartridge::header::{CartridgeHeader, LOGO};
+use gaemboi::cartridge::mbc::{Mbc, Mbc3};
+use gaemboi::cartridge::{Cartridge, CartridgeError};
+use gaemboi::cpu::state::StateError;
+use gaemboi::cpu::{Cpu, CpuError};
+use gaemboi::memory::{Address, Memory};
+
+const BANK_SIZE: usize = 0x4000;
+
+/// A ROM of `banks` 16KB banks whose first byte is the bank number
+fn banked_rom(cartridge_type: u8, rom_size_code: u8, banks: usize) -> Vec<u8> {
+    let mut rom = vec![0; banks * BANK_SIZE];
+    for bank in 0..banks {
+        rom[bank * BANK_SIZE] = bank as u8;
+    }
+
+    rom[0x0104..0x0134].copy_from_slice(&LOGO);
+    rom[0x0134..0x0138].copy_from_slice(b"TEST");
+    rom[0x0147] = cartridge_type;
+    rom[0x0148] = rom_size_code;
+    rom[0x014D] = CartridgeHeader::checksum(&rom);
+    rom
+}
+
+#[test]
+fn mbc1_bank_switch() {
+    let mut cartridge = Cartridge::new(banked_rom(0x01, 0x02, 8)).unwrap();
+    assert_eq!(cartridge.header().title, "TEST");
+
+    // Bank 1 is mapped by default, and selecting bank 0 also maps bank 1
+    assert_eq!(cartridge.read(Address(0x4000)), 1);
+    cartridge.write(Address(0x2000), 0x00);
+    assert_eq!(cartridge.read(Address(0x4000)), 1);
+
+    cartridge.write(Address(0x2000), 0x05);
+    assert_eq!(cartridge.read(Address(0x4000)), 5);
+    assert_eq!(cartridge.read(Address(0x0000)), 0);
+}
+
+#[test]
+fn memory_delegates_cartridge_regions() {
+    let mut memory = Memory::default();
+    memory.insert_cartridge(Cartridge::new(banked_rom(0x01, 0x02, 8)).unwrap());
+
+    memory.write_byte(Address(0x2000), 0x03);
+    memory.write_byte(Address(0xC000), 0x42);
+
+    assert_eq!(memory.read_byte(Address(0x4000)), 3);
+    assert_eq!(
+        memory.read_byte(Address(0x2000)),
+        0x00,
+        "ROM is not writable"
+    );
+    assert_eq!(memory.read_byte(Address(0xC000)), 0x42);
+}
+
+#[test]
+fn header_type_selects_mbc() {
+    let mut mbc1 = Cartridge::new(banked_rom(0x01, 0x02, 8)).unwrap();
+    assert_eq!(mbc1.mbc().name(), "MBC1");
+    mbc1.write(Address(0x2000), 0x02);
+    assert_eq!(mbc1.read(Address(0x4000)), 2);
+
+    let mut rom_only = Cartridge::new(banked_rom(0x00, 0x00, 2)).unwrap();
+    assert_eq!(rom_only.mbc().name(), "ROM only");
+    rom_only.write(Address(0x2000), 0x02);
+    assert_eq!(rom_only.read(Address(0x4000)), 1);
+
+    assert!(Cartridge::new(banked_rom(0xFC, 0x00, 2)).is_err());
+}
+
+#[test]
+fn header_parses_hand_built_bytes() {
+    let mut rom = vec![0; 0x0150];
+    rom[0x0104..0x0134].copy_from_slice(&LOGO);
+    rom[0x0134..0x013D].copy_from_slice(b"POKEMON R");
+    rom[0x0147] = 0x13;
+    rom[0x0148] = 0x05;
+    rom[0x0149] = 0x03;
+    // Precomputed over the bytes above
+    rom[0x014D] = 0x41;
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "POKEMON R");
+    assert_eq!(header.cartridge_type, 0x13);
+    assert_eq!(header.rom_banks(), 64);
+    assert_eq!(header.ram_size(), 0x8000);
+}
+
+#[test]
+fn header_validation_errors() {
+    let mut rom = banked_rom(0x00, 0x00, 2);
+    rom[0x014D] ^= 0xFF;
+    assert_eq!(
+        CartridgeHeader::parse(&rom),
+        Err(CartridgeError::HeaderChecksum {
+            expected: rom[0x014D] ^ 0xFF,
+            actual: rom[0x014D]
+        })
+    );
+
+    let mut rom = banked_rom(0x00, 0x00, 2);
+    rom[0x0104] = 0x00;
+    assert_eq!(CartridgeHeader::parse(&rom), Err(CartridgeError::BadLogo));
+
+    // A valid checksum doesn't make an unknown size code usable
+    for code in [0x09, 0x52, 0xFF] {
+        let rom = banked_rom(0x00, code, 2);
+        assert_eq!(
+            CartridgeHeader::parse(&rom),
+            Err(CartridgeError::UnknownRomSize(code))
+        );
+    }
+    assert_eq!(
+        CartridgeHeader::parse(&banked_rom(0x00, 0x08, 2))
+            .unwrap()
+            .rom_banks(),
+        512
+    );
+}
+
+#[test]
+fn rom_only_cart_ignores_rom_writes() {
+    let mut rom = banked_rom(0x00, 0x00, 2);
+    rom[0x2000] = 0xA5;
+
+    let mut memory = Memory::default();
+    memory.insert_cartridge(Cartridge::new(rom).unwrap());
+
+    memory.write_byte(Address(0x2000), 0x5A);
+    memory.write_byte(Address(0x7FFF), 0x5A);
+
+    assert_eq!(memory.read_byte(Address(0x2000)), 0xA5);
+    assert_eq!(memory.read_byte(Address(0x7FFF)), 0x00);
+    assert_eq!(memory.read_byte(Address(0x4000)), 1);
+}
+
+static RTC_CLOCK: AtomicU64 = AtomicU64::new(1_000_000);
+
+fn rtc_clock() -> u64 {
+    RTC_CLOCK.load(Ordering::SeqCst)
+}
+
+/// Select RTC register `select` and latch the clock
+fn latch_and_read(mbc: &mut Mbc3, select: u8) -> u8 {
+    mbc.write_rom(0x4000, select);
+    mbc.write_rom(0x6000, 0x00);
+    mbc.write_rom(0x6000, 0x01);
+    mbc.read_ram(&[], 0xA000)
+}
+
+#[test]
+fn mbc3_rtc_latches_seconds() {
+    let mut mbc = Mbc3::with_clock(rtc_clock);
+    mbc.write_rom(0x0000, 0x0A);
+    // 1 day, 2 hours, 3 minutes and 4 seconds
+    mbc.set_rtc_seconds(86_400 + 2 * 3600 + 3 * 60 + 4);
+
+    assert_eq!(latch_and_read(&mut mbc, 0x08), 4);
+    assert_eq!(latch_and_read(&mut mbc, 0x09), 3);
+    assert_eq!(latch_and_read(&mut mbc, 0x0A), 2);
+    assert_eq!(latch_and_read(&mut mbc, 0x0B), 1);
+
+    // Time passing doesn't show until the next latch
+    RTC_CLOCK.fetch_add(10, Ordering::SeqCst);
+    mbc.write_rom(0x4000, 0x08);
+    assert_eq!(mbc.read_ram(&[], 0xA000), 4);
+    assert_eq!(latch_and_read(&mut mbc, 0x08), 14);
+
+    // Writing seconds with the halt bit set freezes the counter
+    mbc.write_rom(0x4000, 0x0C);
+    mbc.write_ram(&mut [], 0xA000, 0x40);
+    RTC_CLOCK.fetch_add(10, Ordering::SeqCst);
+    assert_eq!(latch_and_read(&mut mbc, 0x08), 14);
+}
+
+#[test]
+fn mbc3_rtc_survives_state_round_trip() {
+    let mut mbc = Mbc3::with_clock(|| 1_000_000);
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.set_rtc_seconds(59);
+    latch_and_read(&mut mbc, 0x08);
+
+    let mut restored = Mbc3::with_clock(|| 1_000_000);
+    restored.load_state(&mbc.save_state()).unwrap();
+    assert_eq!(restored.read_ram(&[], 0xA000), 59);
+
+    assert!(restored.load_state(&[0x0A, 0x01, 0x08]).is_err());
+}
+
+#[test]
+fn cartridge_ram_dump_and_reload() {
+    let mut rom = banked_rom(0x03, 0x01, 4);
+    // 8KB of RAM
+    rom[0x0149] = 0x02;
+    rom[0x014D] = CartridgeHeader::checksum(&rom);
+
+    let mut cpu = Cpu::default();
+    cpu.memory_mut()
+        .insert_cartridge(Cartridge::new(rom.clone()).unwrap());
+
+    // Enable RAM and write through it
+    cpu.memory_mut().write_byte(Address(0x0000), 0x0A);
+    cpu.memory_mut().write_byte(Address(0xA000), 0x12);
+    cpu.memory_mut().write_byte(Address(0xBFFF), 0x34);
+
+    let saved = cpu.dump_cartridge_ram();
+    assert_eq!(saved.len(), 0x2000);
+
+    // A fresh cartridge starts cleared
+    cpu.memory_mut()
+        .insert_cartridge(Cartridge::new(rom).unwrap());
+    cpu.memory_mut().write_byte(Address(0x0000), 0x0A);
+    assert_eq!(cpu.memory().read_byte(Address(0xA000)), 0x00);
+
+    cpu.load_cartridge_ram(&saved).unwrap();
+    assert_eq!(cpu.memory().read_byte(Address(0xA000)), 0x12);
+    assert_eq!(cpu.memory().read_byte(Address(0xBFFF)), 0x34);
+
+    let err = cpu.load_cartridge_ram(&saved[..0x1000]).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CartridgeError>(),
+        Some(&CartridgeError::RamSizeMismatch {
+            expected: 0x2000,
+            actual: 0x1000
+        })
+    );
+}
+
+#[test]
+fn cartridge_ram_needs_a_cartridge() {
+    let mut cpu = Cpu::default();
+    assert!(cpu.dump_cartridge_ram().is_empty());
+
+    let err = cpu.load_cartridge_ram(&[0; 0x2000]).unwrap_err();
+    assert_eq!(err.downcast_ref::<CpuError>(), Some(&CpuError::NoCartridge));
+}
+
+#[test]
+fn bad_state_leaves_cartridge_untouched() {
+    let mut rom = banked_rom(0x03, 0x01, 4);
+    // 8KB of RAM
+    rom[0x0149] = 0x02;
+    rom[0x014D] = CartridgeHeader::checksum(&rom);
+
+    let mut cpu = Cpu::default();
+    cpu.memory_mut()
+        .insert_cartridge(Cartridge::new(rom).unwrap());
+    cpu.memory_mut().write_byte(Address(0x0000), 0x0A);
+    cpu.memory_mut().write_byte(Address(0xA000), 0x12);
+
+    // Saved with different RAM contents and the second ROM bank mapped
+    let mut state = cpu.state();
+    cpu.memory_mut().write_byte(Address(0xA000), 0x34);
+    cpu.memory_mut().write_byte(Address(0x2000), 0x02);
+    let before = cpu.state();
+
+    // Banking state for the wrong controller
+    let mut wrong_mbc = state.clone();
+    if let Some(cartridge) = wrong_mbc.cartridge.as_mut() {
+        cartridge.mbc.push(0x00);
+    }
+    let err = cpu.set_state(&wrong_mbc).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<StateError>(),
+        Some(&StateError::CartridgeMismatch)
+    );
+    assert_eq!(cpu.state(), before);
+
+    state.memory.pop();
+    let err = cpu.set_state(&state).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<StateError>(),
+        Some(&StateError::MemorySize(0xFFFF))
+    );
+    assert_eq!(cpu.state(), before);
+    assert_eq!(cpu.memory().read_byte(Address(0xA000)), 0x34);
+    assert_eq!(cpu.memory().read_byte(Address(0x4000)), 0x02);
+}
@@ -0,0 +1,238 @@
+use gaemboi::cpu::instructions::{Instruction, InstructionType, Operand, RotateOp, ShiftOp};
+use gaemboi::cpu::registers::Flag;
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+/// A CPU about to run the CB-prefixed `opcode` at `0x0000`
+fn cpu_with_cb(opcode: u8) -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xCB, opcode], Address(0x0000))
+        .unwrap();
+    cpu
+}
+
+#[test]
+fn rotates_decode_from_x0() {
+    let instruction = Instruction::decode_cb(0x1E).unwrap();
+    assert_eq!(
+        *instruction.itype(),
+        InstructionType::Rotate {
+            op: RotateOp::Rr,
+            operand: Operand::Indirect(register!(HL))
+        }
+    );
+    assert_eq!(instruction.cycles(), 4);
+    assert_eq!(instruction.length(), 2);
+}
+
+#[test]
+fn rlc_moves_bit_7_to_carry_and_bit_0() {
+    // RLC B
+    let mut cpu = cpu_with_cb(0x00);
+    cpu.registers_mut().write(register!(B), 0x85);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x0B);
+    assert!(cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+}
+
+#[test]
+fn rr_pulls_carry_into_bit_7() {
+    // RR A
+    let mut cpu = cpu_with_cb(0x1F);
+    cpu.registers_mut().write(register!(A), 0x02);
+    cpu.registers_mut().set_flag(Flag::Carry, true);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x81);
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn rl_through_hl_sets_zero() {
+    // RL (HL)
+    let mut cpu = cpu_with_cb(0x16);
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.memory_mut().write_byte(Address(0xC000), 0x80);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x00);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn shifts_decode_from_x0() {
+    let cases = [
+        (0x20, ShiftOp::Sla),
+        (0x28, ShiftOp::Sra),
+        (0x38, ShiftOp::Srl),
+    ];
+
+    for (opcode, op) in cases {
+        let instruction = Instruction::decode_cb(opcode).unwrap();
+        assert_eq!(
+            *instruction.itype(),
+            InstructionType::Shift {
+                op,
+                operand: Operand::Register8(register!(B))
+            },
+            "opcode {opcode:#04X}"
+        );
+    }
+}
+
+#[test]
+fn sla_fills_bit_0_with_zero() {
+    // SLA C
+    let mut cpu = cpu_with_cb(0x21);
+    cpu.registers_mut().write(register!(C), 0x81);
+    cpu.registers_mut().set_flag(Flag::Carry, false);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(C)), 0x02);
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn sra_keeps_sign_bit() {
+    // SRA B
+    let mut cpu = cpu_with_cb(0x28);
+    cpu.registers_mut().write(register!(B), 0x80);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(B)), 0xC0);
+    assert!(!cpu.registers().flag(Flag::Carry));
+    assert!(!cpu.registers().flag(Flag::Zero));
+}
+
+#[test]
+fn srl_shifts_into_carry_and_zero() {
+    // SRL A
+    let mut cpu = cpu_with_cb(0x3F);
+    cpu.registers_mut().write(register!(A), 0x01);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x00);
+    assert!(cpu.registers().flag(Flag::Carry));
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+}
+
+#[test]
+fn swap_exchanges_nibbles_and_clears_flags() {
+    // SWAP D
+    let mut cpu = cpu_with_cb(0x32);
+    cpu.registers_mut().write(register!(D), 0xAB);
+    cpu.registers_mut().write(register!(F), 0xF0);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(D)), 0xBA);
+    assert_eq!(cpu.registers().fetch(register!(F)), 0x00);
+}
+
+#[test]
+fn swap_zero_sets_zero() {
+    // SWAP (HL)
+    let mut cpu = cpu_with_cb(0x36);
+    cpu.registers_mut().write(register!(HL), 0xC000);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x00);
+    assert_eq!(cpu.registers().fetch(register!(F)), Flag::Zero.bit());
+}
+
+#[test]
+fn bit_decodes_from_x1() {
+    let instruction = Instruction::decode_cb(0x7E).unwrap();
+    assert_eq!(
+        *instruction.itype(),
+        InstructionType::Bit {
+            bit: 7,
+            operand: Operand::Indirect(register!(HL))
+        }
+    );
+    assert_eq!(instruction.cycles(), 3);
+}
+
+#[test]
+fn bit_set_clears_zero() {
+    // BIT 7,H
+    let mut cpu = cpu_with_cb(0x7C);
+    cpu.registers_mut().write(register!(H), 0x80);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+    cpu.registers_mut().set_flag(Flag::Sub, true);
+    cpu.registers_mut().set_flag(Flag::Carry, true);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(H)), 0x80);
+    assert!(!cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn bit_clear_sets_zero() {
+    // BIT 0,A
+    let mut cpu = cpu_with_cb(0x47);
+    cpu.registers_mut().write(register!(A), 0xFE);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), 0xFE);
+    assert!(cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn set_and_res_leave_flags_alone() {
+    // SET 3,B; RES 7,A
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xCB, 0xD8, 0xCB, 0xBF], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(B), 0x00);
+    cpu.registers_mut().write(register!(A), 0xFF);
+    cpu.registers_mut().write(register!(F), 0xA0);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x08);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x7F);
+
+    assert_eq!(cpu.registers().fetch(register!(F)), 0xA0);
+}
+
+#[test]
+fn set_through_hl_writes_back() {
+    // SET 0,(HL)
+    let mut cpu = cpu_with_cb(0xC6);
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.memory_mut().write_byte(Address(0xC000), 0x80);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x81);
+}
+
+#[test]
+fn whole_cb_page_decodes() {
+    for opcode in 0..=0xFF {
+        let instruction = Instruction::decode_cb(opcode).unwrap();
+        assert_eq!(instruction.length(), 2, "opcode {opcode:#04X}");
+    }
+}
@@ -0,0 +1,315 @@
+use gaemboi::cpu::instructions::{DecodeError, Instruction, Operand};
+use gaemboi::cpu::interrupts::{INTERRUPT_ENABLE, INTERRUPT_FLAG};
+use gaemboi::cpu::registers::{Register16, Register8, Registers};
+use gaemboi::cpu::{Cpu, CpuError, Execute};
+use gaemboi::memory::{Address, Memory};
+use gaemboi::register;
+use gaemboi::timer::{DIV, TAC, TIMA};
+
+#[test]
+fn pc_accessor_tracks_step() {
+    let mut cpu = Cpu::default();
+    // NOP; HALT
+    cpu.load_rom_with_entry(&[0x00, 0x76], Address(0x0000))
+        .unwrap();
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), cpu.registers().fetch(register!(PC)));
+    assert_eq!(cpu.pc(), 0x0001);
+    assert!(!cpu.is_halted());
+
+    cpu.step().unwrap();
+    assert!(cpu.is_halted());
+    assert!(!cpu.is_stopped());
+    assert_eq!(cpu.sp(), cpu.registers().fetch(register!(SP)));
+}
+
+#[test]
+fn stack_dump_after_pushes() {
+    let mut cpu = Cpu::default();
+    // PUSH BC; PUSH DE
+    cpu.load_rom_with_entry(&[0xC5, 0xD5], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.registers_mut().write(register!(BC), 0x1234);
+    cpu.registers_mut().write(register!(DE), 0xABCD);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.sp(), 0xFFFA);
+    assert_eq!(cpu.stack_dump(2), vec![0xABCD, 0x1234]);
+}
+
+#[test]
+fn stack_dump_wraps_near_top_of_memory() {
+    let mut cpu = Cpu::default();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.memory_mut().write_word(Address(0xFFFE), 0xBEEF);
+    cpu.memory_mut().write_word(Address(0x0000), 0x1234);
+
+    assert_eq!(cpu.stack_dump(2), vec![0xBEEF, 0x1234]);
+
+    // Deeper than the address space, the dump just keeps wrapping round
+    let dump = cpu.stack_dump(0x8001);
+    assert_eq!(dump.len(), 0x8001);
+    assert_eq!(dump[0x8000], 0xBEEF);
+}
+
+/// HALT; LD B,A with the timer interrupt enabled but IME clear, so a TIMA
+/// overflow wakes the CPU without vectoring
+fn halted_on_timer(tac: u8) -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x76, 0x47], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), 0x77);
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 1 << 2);
+    cpu.memory_mut().write_byte(TAC, tac);
+    cpu
+}
+
+#[test]
+fn halt_fast_forward_matches_stepping() {
+    let mut fast = halted_on_timer(0b101);
+    assert_eq!(fast.run_for_cycles(3000).unwrap(), 3000);
+
+    let mut slow = halted_on_timer(0b101);
+    while slow.cycles_elapsed() < 3000 {
+        slow.step().unwrap();
+    }
+
+    assert!(!fast.is_halted());
+    assert_eq!(fast.registers().fetch(register!(B)), 0x77);
+    assert_eq!(fast.registers(), slow.registers());
+    assert_eq!(fast.cycles_elapsed(), slow.cycles_elapsed());
+    for reg in [DIV, TIMA, INTERRUPT_FLAG] {
+        assert_eq!(fast.memory().read_byte(reg), slow.memory().read_byte(reg));
+    }
+}
+
+#[test]
+fn halt_without_wake_source_skips_to_budget() {
+    // Far more cycles than could be stepped one at a time in a test
+    let budget = 1 << 40;
+
+    let mut cpu = halted_on_timer(0b000);
+    assert_eq!(cpu.run_for_cycles(budget).unwrap(), budget);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.pc(), 0x0001);
+}
+
+#[test]
+fn stack_wrapping_is_an_error() {
+    let mut cpu = Cpu::default();
+    // PUSH BC; POP BC
+    cpu.load_rom_with_entry(&[0xC5, 0xC1], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0x0001);
+
+    let err = cpu.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::StackOverflow { sp: 0x0001 })
+    );
+
+    cpu.registers_mut().write(register!(SP), 0xFFFF);
+    let err = cpu.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::StackUnderflow { sp: 0xFFFF })
+    );
+}
+
+#[test]
+fn cb_prefix_fetches_second_page_opcode() {
+    let mut cpu = Cpu::default();
+    // RLC B
+    cpu.load_rom_with_entry(&[0xCB, 0x00], Address(0x0000))
+        .unwrap();
+
+    // Prefix and opcode fetches, no internal work
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.pc(), 0x0002);
+    assert_eq!(cpu.memory_accesses(), 2);
+}
+
+#[test]
+fn push_bc_pop_de_transfers_value() {
+    let mut cpu = Cpu::default();
+    // PUSH BC; POP DE
+    cpu.load_rom_with_entry(&[0xC5, 0xD1], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.registers_mut().write(register!(BC), 0xBEEF);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.memory().read_byte(Address(0xFFFD)), 0xBE);
+    assert_eq!(cpu.memory().read_byte(Address(0xFFFC)), 0xEF);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.registers().fetch(register!(DE)), 0xBEEF);
+    assert_eq!(cpu.sp(), 0xFFFE);
+}
+
+#[test]
+fn pop_af_masks_low_nibble_of_f() {
+    let mut cpu = Cpu::default();
+    // POP AF
+    cpu.load_rom_with_entry(&[0xF1], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFC);
+    cpu.memory_mut()
+        .write(Address(0xFFFC), &[0xFF, 0x12])
+        .unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(AF)), 0x12F0);
+}
+
+#[test]
+fn immediate_word_wraps_past_top_of_memory() {
+    let mut cpu = Cpu::default();
+    // JP 0x1234 with the high address byte wrapped round to 0x0000
+    cpu.memory_mut()
+        .write(Address(0xFFFE), &[0xC3, 0x34])
+        .unwrap();
+    cpu.memory_mut().write_byte(Address(0x0000), 0x12);
+    cpu.registers_mut().write(register!(PC), 0xFFFE);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.pc(), 0x1234);
+}
+
+#[test]
+fn stop_consumes_padding_byte() {
+    let mut cpu = Cpu::default();
+    // STOP; NOP
+    cpu.load_rom_with_entry(&[0x10, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.pc(), 0x0002);
+    assert!(cpu.is_stopped());
+
+    // Nothing runs while stopped
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0002);
+}
+
+#[test]
+fn step_reports_cycles_and_keeps_a_total() {
+    let mut cpu = Cpu::default();
+    // NOP; JP C,0x0010 (taken); 0x0010: SWAP A
+    cpu.load_rom_with_entry(&[0x00, 0xDA, 0x10, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut()
+        .write(Address(0x0010), &[0xCB, 0x37])
+        .unwrap();
+    cpu.registers_mut().write(register!(F), 0x10);
+
+    assert_eq!(cpu.step().unwrap(), 1);
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.step().unwrap(), 2);
+
+    assert_eq!(cpu.cycles_elapsed(), 7);
+}
+
+#[test]
+fn new_starts_from_given_state() {
+    let mut registers = Registers::default();
+    registers.write(register!(PC), 0x0100);
+    registers.write(register!(BC), 0x1234);
+
+    let mut memory = Memory::default();
+    // LD D,B
+    memory.write_byte(Address(0x0100), 0x50);
+
+    let mut cpu = Cpu::new(registers.clone(), memory);
+    assert_eq!(cpu.registers(), &registers);
+    assert_eq!(cpu.memory().read_byte(Address(0x0100)), 0x50);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(D)), 0x12);
+
+    cpu.registers_mut().write(register!(A), 0x77);
+    cpu.memory_mut().write_byte(Address(0xC000), 0x01);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x77);
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x01);
+}
+
+#[test]
+fn execute_runs_hand_built_instruction() {
+    let mut cpu = Cpu::default();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.registers_mut().write(register!(E), 0x5A);
+
+    let load = Instruction::load(
+        Operand::Indirect(Register16::HL),
+        Operand::Register8(Register8::E),
+    );
+    assert_eq!(cpu.execute(load).unwrap(), 2);
+
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x5A);
+    // Nothing was fetched and the clock is left to step
+    assert_eq!(cpu.pc(), 0x0000);
+    assert_eq!(cpu.cycles_elapsed(), 0);
+}
+
+#[test]
+fn unknown_opcode_is_a_typed_error() {
+    // 0xD3 is one of the holes in the opcode map
+    let err = Instruction::decode(0xD3).unwrap_err();
+    assert_eq!(
+        err,
+        DecodeError::UnknownOpcode {
+            opcode: 0xD3,
+            cb: false
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "failed to match opcode 0xD3 (x=3, y=2, z=3)"
+    );
+
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xD3], Address(0x0000)).unwrap();
+    let err = cpu.step().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DecodeError>(),
+        Some(DecodeError::UnknownOpcode { opcode: 0xD3, .. })
+    ));
+}
+
+#[test]
+fn nop_sled_runs_for_cycle_budget() {
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x00; 0x100], Address(0x0000))
+        .unwrap();
+
+    assert_eq!(cpu.run_for_cycles(100).unwrap(), 100);
+    assert_eq!(cpu.pc(), 100);
+    assert_eq!(cpu.cycles_elapsed(), 100);
+}
+
+#[test]
+fn step_instruction_returns_what_ran() {
+    let mut cpu = Cpu::default();
+    // INC A; HALT
+    cpu.load_rom_with_entry(&[0x3C, 0x76], Address(0x0000))
+        .unwrap();
+
+    assert_eq!(
+        cpu.step_instruction().unwrap(),
+        Some(Instruction::decode(0x3C).unwrap())
+    );
+    assert_eq!(
+        cpu.step_instruction().unwrap(),
+        Some(Instruction::decode(0x76).unwrap())
+    );
+
+    // Idling while halted runs nothing but still takes time
+    assert_eq!(cpu.step_instruction().unwrap(), None);
+    assert_eq!(cpu.cycles_elapsed(), 3);
+}
@@ -0,0 +1,99 @@
+use gaemboi::cpu::watch::{WatchHit, WatchKind};
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+/// INC A; INC A; JP 0x0000
+fn looping_cpu() -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x3C, 0x3C, 0xC3, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu
+}
+
+#[test]
+fn run_until_break_stops_before_breakpoint() {
+    let mut cpu = looping_cpu();
+    cpu.add_breakpoint(Address(0x0002));
+
+    assert_eq!(cpu.run_until_break().unwrap(), Address(0x0002));
+    assert_eq!(cpu.pc(), 0x0002);
+    assert_eq!(cpu.registers().fetch(register!(A)), 2);
+
+    // Continuing runs past the current breakpoint and round the loop
+    assert_eq!(cpu.run_until_break().unwrap(), Address(0x0002));
+    assert_eq!(cpu.registers().fetch(register!(A)), 4);
+}
+
+#[test]
+fn removed_breakpoint_no_longer_stops() {
+    let mut cpu = looping_cpu();
+    cpu.add_breakpoint(Address(0x0001));
+    cpu.add_breakpoint(Address(0x0002));
+    cpu.remove_breakpoint(Address(0x0001));
+
+    assert_eq!(cpu.run_until_break().unwrap(), Address(0x0002));
+}
+
+#[test]
+fn run_for_cycles_stops_at_breakpoint() {
+    let mut cpu = looping_cpu();
+    cpu.add_breakpoint(Address(0x0002));
+
+    assert_eq!(cpu.run_for_cycles(1000).unwrap(), 2);
+    assert_eq!(cpu.pc(), 0x0002);
+}
+
+#[test]
+fn write_watchpoint_fires_on_store() {
+    let mut cpu = Cpu::default();
+    // LD A,(0xC000); INC A; LD (0xC000),A; NOP
+    cpu.load_rom_with_entry(
+        &[0xFA, 0x00, 0xC0, 0x3C, 0xEA, 0x00, 0xC0, 0x00],
+        Address(0x0000),
+    )
+    .unwrap();
+    cpu.memory_mut().write_byte(Address(0xC000), 0x41);
+    cpu.add_watchpoint(Address(0xC000), WatchKind::Write);
+
+    // The load before it only reads the watched address
+    assert_eq!(cpu.run_until_break().unwrap(), Address(0x0007));
+    assert_eq!(
+        cpu.take_watch_hit(),
+        Some(WatchHit {
+            pc: Address(0x0004),
+            addr: Address(0xC000),
+            kind: WatchKind::Write,
+            value: 0x42,
+        })
+    );
+    assert_eq!(cpu.take_watch_hit(), None);
+}
+
+#[test]
+fn read_and_access_watchpoints() {
+    let mut cpu = Cpu::default();
+    // LD A,(HL); LD (HL),A; NOP
+    cpu.load_rom_with_entry(&[0x7E, 0x77, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.memory_mut().write_byte(Address(0xC000), 0x99);
+    cpu.add_watchpoint(Address(0xC000), WatchKind::Read);
+
+    assert_eq!(cpu.run_for_cycles(1000).unwrap(), 2);
+    let hit = cpu.take_watch_hit().unwrap();
+    assert_eq!(
+        (hit.pc, hit.kind, hit.value),
+        (Address(0x0000), WatchKind::Read, 0x99)
+    );
+
+    cpu.add_watchpoint(Address(0xC000), WatchKind::Access);
+    cpu.step().unwrap();
+    assert_eq!(cpu.take_watch_hit().unwrap().kind, WatchKind::Write);
+
+    // Peeking from outside the CPU doesn't count
+    cpu.remove_watchpoint(Address(0xC000));
+    cpu.add_watchpoint(Address(0x0002), WatchKind::Access);
+    assert_eq!(cpu.memory().read_byte(Address(0x0002)), 0x00);
+    assert_eq!(cpu.take_watch_hit(), None);
+}
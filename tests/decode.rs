@@ -0,0 +1,158 @@
+use gaemboi::cpu::instructions::{Instruction, InstructionType, Operand, CB_PREFIX};
+use gaemboi::cpu::registers::{Register16, Register8};
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+/// Opcodes with no instruction on the real hardware
+const ILLEGAL: [u8; 11] = [
+    0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+];
+
+#[test]
+fn every_legal_opcode_decodes() {
+    let undecoded: Vec<u8> = (0..=255)
+        .filter(|&opcode| Instruction::decode(opcode).is_err())
+        .collect();
+
+    let mut expected = ILLEGAL.to_vec();
+    expected.push(CB_PREFIX);
+    expected.sort();
+
+    assert_eq!(undecoded, expected);
+    assert_eq!(256 - undecoded.len(), 244);
+}
+
+#[test]
+fn every_cb_opcode_decodes() {
+    assert!((0..=255).all(|opcode| Instruction::decode_cb(opcode).is_ok()));
+}
+
+#[test]
+fn immediate_loads_report_length_and_cycles() {
+    // LD B,d8 and LD (HL),d8
+    let ld_b = Instruction::decode(0x06).unwrap();
+    assert_eq!((ld_b.length(), ld_b.cycles()), (2, 2));
+    let ld_hl = Instruction::decode(0x36).unwrap();
+    assert_eq!((ld_hl.length(), ld_hl.cycles()), (2, 3));
+
+    // LD BC,d16 and LD SP,d16
+    for opcode in [0x01, 0x31] {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!((instruction.length(), instruction.cycles()), (3, 3));
+    }
+}
+
+#[test]
+fn execution_matches_length_and_cycles() {
+    for opcode in 0..=255 {
+        let Ok(instruction) = Instruction::decode(opcode) else {
+            continue;
+        };
+        if matches!(
+            instruction.itype(),
+            InstructionType::Jump { .. }
+                | InstructionType::JumpRelative { .. }
+                | InstructionType::JumpHl
+                | InstructionType::Call { .. }
+                | InstructionType::Rst(_)
+                | InstructionType::Return { .. }
+        ) {
+            continue;
+        }
+
+        let mut cpu = Cpu::default();
+        // Operands point into work RAM, clear of the program
+        cpu.load_rom_with_entry(&[opcode, 0x00, 0xC0], Address(0x0000))
+            .unwrap();
+        cpu.registers_mut().write(register!(SP), 0xDFF0);
+        cpu.registers_mut().write(register!(HL), 0xC100);
+
+        let cycles = cpu.step().unwrap();
+        assert_eq!(
+            cpu.pc(),
+            instruction.length() as u16,
+            "{instruction} ({opcode:#04X}) length"
+        );
+        assert_eq!(
+            cycles,
+            instruction.cycles(),
+            "{instruction} ({opcode:#04X}) cycles"
+        );
+    }
+}
+
+#[test]
+fn only_0x00_decodes_to_nop() {
+    let nop = Instruction::decode(0x00).unwrap();
+    assert_eq!(*nop.itype(), InstructionType::Nop);
+    assert_eq!((nop.length(), nop.cycles()), (1, 1));
+
+    for opcode in 0x01..=0xFF {
+        if let Ok(instruction) = Instruction::decode(opcode) {
+            assert_ne!(*instruction.itype(), InstructionType::Nop, "{opcode:#04X}");
+        }
+    }
+
+    // Self loads like LD B,B are real loads, not NOP aliases
+    assert!(matches!(
+        Instruction::decode(0x40).unwrap().itype(),
+        InstructionType::Load { .. }
+    ));
+}
+
+#[test]
+fn halt_sits_inside_the_load_block() {
+    // LD (HL),(HL) doesn't exist, its encoding is HALT
+    let halt = Instruction::decode(0x76).unwrap();
+    assert_eq!(*halt.itype(), InstructionType::Halt);
+
+    let hl = Operand::Indirect(Register16::HL);
+    let a = Operand::Register8(Register8::A);
+    let b = Operand::Register8(Register8::B);
+    for (opcode, dst, src) in [(0x77, hl, a), (0x46, b, hl), (0x7E, a, hl), (0x70, hl, b)] {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert!(
+            matches!(
+                instruction.itype(),
+                InstructionType::Load { dst: d, src: s, followup: None } if *d == dst && *s == src
+            ),
+            "{opcode:#04X} decoded as {instruction}"
+        );
+        assert_eq!(instruction.cycles(), 2, "{opcode:#04X}");
+    }
+
+    // Every other opcode in 0x40..=0x7F is a plain register load
+    for opcode in (0x40..=0x7F).filter(|&opcode| opcode != 0x76) {
+        assert!(
+            matches!(
+                Instruction::decode(opcode).unwrap().itype(),
+                InstructionType::Load { .. }
+            ),
+            "{opcode:#04X}"
+        );
+    }
+}
+
+#[test]
+fn r_table_follows_hardware_encoding() {
+    let expected = [
+        Operand::Register8(Register8::B),
+        Operand::Register8(Register8::C),
+        Operand::Register8(Register8::D),
+        Operand::Register8(Register8::E),
+        Operand::Register8(Register8::H),
+        Operand::Register8(Register8::L),
+        Operand::Indirect(Register16::HL),
+        Operand::Register8(Register8::A),
+    ];
+
+    for (idx, operand) in (0..8u8).zip(expected) {
+        assert_eq!(Operand::from_r_table(idx), operand, "index {idx}");
+        // LD r,A with r in bits 3-5, ALU ops with r in bits 0-2
+        let load = Instruction::decode(0x47 | idx << 3).unwrap();
+        let add = Instruction::decode(0x80 | idx).unwrap();
+        assert!(matches!(load.itype(), InstructionType::Load { dst, .. } if *dst == operand));
+        assert!(matches!(add.itype(), InstructionType::Arith8 { src, .. } if *src == operand));
+    }
+}
@@ -0,0 +1,179 @@
+use gaemboi::cpu::instructions::{ArithOp, DecodeError, Instruction, InstructionType};
+use gaemboi::cpu::Cpu;
+use gaemboi::disassembler::{symbol_for, InstructionStream};
+use gaemboi::memory::Address;
+
+#[test]
+fn vectors_have_symbols() {
+    assert_eq!(symbol_for(Address(0x0040)), Some("VBlank"));
+    assert_eq!(symbol_for(Address(0x0038)), Some("RST_38"));
+    assert_eq!(symbol_for(Address(0x0100)), Some("Entry"));
+    assert_eq!(symbol_for(Address(0x0041)), None);
+}
+
+#[test]
+fn stream_decodes_offsets_and_lengths() {
+    // LD A,(0xC000); JR -2; ADD A,B; RL C; NOP
+    let bytes = [0xFA, 0x00, 0xC0, 0x18, 0xFE, 0x80, 0xCB, 0x11, 0x00];
+
+    let decoded: Vec<_> = InstructionStream::new(&bytes)
+        .map(|(offset, instruction, length)| (offset, instruction.ok(), length))
+        .collect();
+
+    let expected = vec![
+        (0, Instruction::decode(0xFA).ok(), 3),
+        (3, Instruction::decode(0x18).ok(), 2),
+        (5, Instruction::decode(0x80).ok(), 1),
+        (6, Instruction::decode_cb(0x11).ok(), 2),
+        (8, Some(Instruction::nop()), 1),
+    ];
+    assert_eq!(decoded, expected);
+    assert!(matches!(
+        *expected[2].1.unwrap().itype(),
+        InstructionType::Arith8 {
+            op: ArithOp::Add,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn instructions_display_as_mnemonics() {
+    let cases = [
+        (0x41, "LD B,C"),
+        (0x86, "ADD A,(HL)"),
+        (0x90, "SUB B"),
+        (0x20, "JR NZ,r8"),
+        (0x18, "JR r8"),
+        (0xC3, "JP a16"),
+        (0xC8, "RET Z"),
+        (0xD9, "RETI"),
+        (0xFF, "RST 38H"),
+        (0x22, "LD (HL+),A"),
+        (0xF0, "LDH A,(a8)"),
+        (0xE2, "LD (C),A"),
+        (0x31, "LD SP,d16"),
+        (0xEA, "LD (a16),A"),
+        (0xFE, "CP d8"),
+        (0x07, "RLCA"),
+        (0xC5, "PUSH BC"),
+    ];
+
+    for (opcode, expected) in cases {
+        assert_eq!(Instruction::decode(opcode).unwrap().to_string(), expected);
+    }
+
+    assert_eq!(Instruction::decode_cb(0x7C).unwrap().to_string(), "BIT 7,H");
+    assert_eq!(
+        Instruction::decode_cb(0x3E).unwrap().to_string(),
+        "SRL (HL)"
+    );
+}
+
+#[test]
+fn immediates_display_with_their_values() {
+    let cases: [(u8, &[u8], &str); 12] = [
+        (0x20, &[0xFE], "JR NZ,-2"),
+        (0x18, &[0x7F], "JR +127"),
+        (0x18, &[0x80], "JR -128"),
+        (0xE8, &[0xFE], "ADD SP,-2"),
+        (0xF8, &[0x05], "LD HL,SP+5"),
+        (0xF8, &[0xFB], "LD HL,SP-5"),
+        (0xC3, &[0x50, 0x01], "JP 0150H"),
+        (0xCC, &[0x00, 0x40], "CALL Z,4000H"),
+        (0x08, &[0x00, 0xC0], "LD (C000H),SP"),
+        (0x31, &[0xFE, 0xFF], "LD SP,FFFEH"),
+        (0xE0, &[0x44], "LDH (FF44H),A"),
+        (0xFE, &[0x90], "CP 90H"),
+    ];
+
+    for (opcode, operands, expected) in cases {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(instruction.with_operands(operands).to_string(), expected);
+    }
+
+    // Missing operand bytes fall back to placeholders
+    let jump = Instruction::decode(0xC3).unwrap();
+    assert_eq!(jump.with_operands(&[0x50]).to_string(), "JP a16");
+}
+
+#[test]
+fn disassemble_range_lists_program() {
+    let mut cpu = Cpu::default();
+    // LD A,0x42; illegal 0xD3; LD (0xC000),A; SET 7,H; JR -10
+    cpu.load_rom(&[0x3E, 0x42, 0xD3, 0xEA, 0x00, 0xC0, 0xCB, 0xFC, 0x18, 0xF6])
+        .unwrap();
+
+    let listing: Vec<_> = cpu
+        .disassemble_range(Address(0x0000), 5)
+        .into_iter()
+        .map(|(addr, instruction, text)| (addr.0, instruction.is_some(), text))
+        .collect();
+
+    assert_eq!(
+        listing,
+        [
+            (0x0000, true, "RST_00: LD A,42H".to_string()),
+            (0x0002, false, "DB D3H".to_string()),
+            (0x0003, true, "LD (C000H),A".to_string()),
+            (0x0006, true, "SET 7,H".to_string()),
+            (0x0008, true, "RST_08: JR -10".to_string()),
+        ]
+    );
+
+    // Nothing was fetched over the bus
+    assert_eq!(cpu.memory_accesses(), 0);
+}
+
+#[test]
+fn disassemble_range_labels_known_addresses() {
+    let mut cpu = Cpu::default();
+    // 0x0038: RST 38H; 0x0040: RETI; 0x0041: NOP
+    cpu.memory_mut().write_byte(Address(0x0038), 0xFF);
+    cpu.memory_mut()
+        .write(Address(0x0040), &[0xD9, 0x00])
+        .unwrap();
+    // 0x0100: NOP; JP 0x0150
+    cpu.memory_mut()
+        .write(Address(0x0100), &[0x00, 0xC3, 0x50, 0x01])
+        .unwrap();
+
+    let text = |start, count| -> Vec<String> {
+        cpu.disassemble_range(Address(start), count)
+            .into_iter()
+            .map(|(_, _, text)| text)
+            .collect()
+    };
+
+    assert_eq!(text(0x0038, 1), ["RST_38: RST 38H"]);
+    assert_eq!(text(0x0040, 2), ["VBlank: RETI", "NOP"]);
+    assert_eq!(text(0x0100, 2), ["Entry: NOP", "JP 0150H"]);
+}
+
+#[test]
+fn stream_yields_typed_decode_errors() {
+    // Illegal 0xD3, then a CB prefix cut off by the end of the slice
+    let errors: Vec<_> = InstructionStream::new(&[0xD3, 0xCB])
+        .map(|(offset, instruction, _)| (offset, instruction.unwrap_err()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        [
+            (
+                0,
+                DecodeError::UnknownOpcode {
+                    opcode: 0xD3,
+                    cb: false
+                }
+            ),
+            (
+                1,
+                DecodeError::UnknownOpcode {
+                    opcode: 0xCB,
+                    cb: false
+                }
+            ),
+        ]
+    );
+}
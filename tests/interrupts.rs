@@ -0,0 +1,209 @@
+use gaemboi::cpu::interrupts::{Interrupt, INTERRUPT_ENABLE, INTERRUPT_FLAG};
+use gaemboi::cpu::{Cpu, CpuError};
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+#[test]
+fn interrupt_waits_for_instruction_after_ei() {
+    let mut cpu = Cpu::default();
+    // EI; LD B,A; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x47, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.registers_mut().write(register!(A), 0x99);
+
+    // VBlank is pending before EI and stays pending throughout
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x01);
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x01);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0001);
+    assert!(!cpu.ime());
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0002, "instruction after EI must run first");
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x99);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0040);
+    assert!(!cpu.ime());
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x00);
+    assert_eq!(cpu.sp(), 0xFFFC);
+    assert_eq!(
+        cpu.memory().read(Address(0xFFFC), 2).unwrap(),
+        &[0x02, 0x00]
+    );
+}
+
+#[test]
+fn ei_is_delayed_and_di_is_immediate() {
+    let mut cpu = Cpu::default();
+    // EI; NOP; DI; EI; DI; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x00, 0xF3, 0xFB, 0xF3, 0x00], Address(0x0000))
+        .unwrap();
+
+    cpu.step().unwrap();
+    assert!(!cpu.ime());
+
+    cpu.step().unwrap();
+    assert!(cpu.ime());
+
+    cpu.step().unwrap();
+    assert!(!cpu.ime());
+
+    // DI straight after EI cancels the pending enable
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert!(!cpu.ime());
+}
+
+#[test]
+fn dispatch_takes_highest_priority_first() {
+    let mut cpu = Cpu::default();
+    // EI; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert!(cpu.ime());
+
+    // Timer and VBlank both requested and enabled
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x05);
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x05);
+
+    assert_eq!(cpu.step().unwrap(), 5);
+
+    assert_eq!(cpu.pc(), 0x0040);
+    assert!(!cpu.ime());
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x04);
+    assert_eq!(cpu.stack_dump(1), vec![0x0002]);
+}
+
+#[test]
+fn disabled_interrupt_is_not_dispatched() {
+    let mut cpu = Cpu::default();
+    // EI; NOP; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    // Serial requested but only VBlank enabled
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x01);
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x08);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0003);
+    assert!(cpu.ime());
+}
+
+#[test]
+fn halt_wakes_on_pending_interrupt_without_ime() {
+    let mut cpu = Cpu::default();
+    // HALT; INC A
+    cpu.load_rom_with_entry(&[0x76, 0x3C], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x01);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.pc(), 0x0001);
+
+    // With IME clear the CPU resumes after HALT instead of vectoring
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x01);
+    cpu.step().unwrap();
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.pc(), 0x0002);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x01);
+}
+
+#[test]
+fn halt_bug_reads_next_byte_twice() {
+    let mut cpu = Cpu::default();
+    // HALT; INC A; NOP
+    cpu.load_rom_with_entry(&[0x76, 0x3C, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x01);
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x01);
+
+    // IME is clear and VBlank already pending, so HALT doesn't halt
+    cpu.step().unwrap();
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.pc(), 0x0001);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0001);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x01);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0002);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x02);
+}
+
+#[test]
+fn request_interrupt_sets_only_its_bit() {
+    let mut cpu = Cpu::default();
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x11);
+
+    cpu.request_interrupt(Interrupt::Timer);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x15);
+
+    // Already requested sources stay requested
+    cpu.request_interrupt(Interrupt::VBlank);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x15);
+
+    let bits = Interrupt::ALL.map(Interrupt::bit);
+    assert_eq!(bits, [0x01, 0x02, 0x04, 0x08, 0x10]);
+    let vectors = Interrupt::ALL.map(Interrupt::vector);
+    assert_eq!(vectors, [0x0040, 0x0048, 0x0050, 0x0058, 0x0060]);
+}
+
+#[test]
+fn requested_interrupt_is_dispatched() {
+    let mut cpu = Cpu::default();
+    // EI; NOP; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.memory_mut()
+        .write_byte(INTERRUPT_ENABLE, Interrupt::Serial.bit());
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    cpu.request_interrupt(Interrupt::Serial);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), Interrupt::Serial.vector());
+}
+
+#[test]
+fn failed_dispatch_keeps_request_and_ime() {
+    let mut cpu = Cpu::default();
+    // EI; NOP; NOP
+    cpu.load_rom_with_entry(&[0xFB, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    // No room to push PC
+    cpu.registers_mut().write(register!(SP), 0x0001);
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 0x01);
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0x01);
+
+    let err = cpu.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::StackOverflow { sp: 0x0001 })
+    );
+    assert!(cpu.ime());
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x01);
+    assert_eq!(cpu.pc(), 0x0002);
+
+    // With the stack fixed up the same interrupt is taken
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0040);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0x00);
+}
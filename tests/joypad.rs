@@ -0,0 +1,68 @@
+use gaemboi::cpu::interrupts::INTERRUPT_FLAG;
+use gaemboi::cpu::Cpu;
+use gaemboi::joypad::{Button, P1};
+use gaemboi::memory::Address;
+
+#[test]
+fn pressed_a_reads_low_in_action_group() {
+    let mut cpu = Cpu::default();
+    cpu.set_button(Button::A, true);
+
+    // Select the action buttons
+    cpu.memory_mut().write_byte(P1, 0x10);
+    assert_eq!(cpu.memory().read_byte(P1), 0xDE);
+
+    // A shares bit 0 with Right, which isn't held
+    cpu.memory_mut().write_byte(P1, 0x20);
+    assert_eq!(cpu.memory().read_byte(P1), 0xEF);
+
+    cpu.set_button(Button::A, false);
+    cpu.memory_mut().write_byte(P1, 0x10);
+    assert_eq!(cpu.memory().read_byte(P1), 0xDF);
+}
+
+#[test]
+fn nothing_selected_reads_high() {
+    let mut cpu = Cpu::default();
+    cpu.set_button(Button::Down, true);
+    cpu.set_button(Button::Start, true);
+
+    assert_eq!(cpu.memory().read_byte(P1), 0xFF);
+}
+
+#[test]
+fn press_in_selected_group_requests_interrupt() {
+    let mut cpu = Cpu::default();
+    cpu.memory_mut().write_byte(P1, 0x20);
+
+    // Start isn't in the selected direction group
+    cpu.set_button(Button::Start, true);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 0);
+
+    cpu.set_button(Button::Up, true);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 1 << 4);
+    assert_eq!(cpu.memory().read_byte(P1), 0xEB);
+
+    // Selecting the action group pulls Start's line low too
+    cpu.memory_mut().write_byte(INTERRUPT_FLAG, 0);
+    cpu.memory_mut().write_byte(P1, 0x00);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 1 << 4);
+    assert_eq!(cpu.memory().read_byte(P1), 0xC3);
+}
+
+#[test]
+fn button_press_ends_stop() {
+    let mut cpu = Cpu::default();
+    // STOP; INC A
+    cpu.load_rom_with_entry(&[0x10, 0x00, 0x3C], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(P1, 0x10);
+
+    cpu.step().unwrap();
+    assert!(cpu.is_stopped());
+
+    cpu.set_button(Button::B, true);
+    assert!(!cpu.is_stopped());
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0003);
+}
@@ -0,0 +1,286 @@
+use gaemboi::cpu::instructions::{Condition, Instruction, InstructionType};
+use gaemboi::cpu::registers::{Flag, Registers};
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+fn run_jr(entry: u16, offset: u8) -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.memory_mut()
+        .write(Address(entry), &[0x18, offset])
+        .unwrap();
+    cpu.registers_mut().write(register!(PC), entry);
+    cpu.step().unwrap();
+    cpu
+}
+
+#[test]
+fn jr_displacement_is_signed() {
+    // The displacement is relative to the address after the operand
+    assert_eq!(run_jr(0x0100, 0x7F).pc(), 0x0102 + 127);
+    assert_eq!(run_jr(0x0100, 0x80).pc(), 0x0102 - 128);
+}
+
+#[test]
+fn jr_minus_two_is_a_self_loop() {
+    let mut cpu = run_jr(0x0100, 0xFE);
+    assert_eq!(cpu.pc(), 0x0100);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0100);
+}
+
+#[test]
+fn jr_is_unconditional() {
+    assert_eq!(
+        *Instruction::decode(0x18).unwrap().itype(),
+        InstructionType::JumpRelative { condition: None }
+    );
+
+    // PC is 0x0102 once the operand has been fetched
+    assert_eq!(run_jr(0x0100, 0x05).pc(), 0x0107);
+}
+
+#[test]
+fn jr_cc_decodes_condition_from_y() {
+    let cases = [
+        (0x20, Condition::NZ),
+        (0x28, Condition::Z),
+        (0x30, Condition::NC),
+        (0x38, Condition::C),
+    ];
+
+    for (opcode, condition) in cases {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(
+            *instruction.itype(),
+            InstructionType::JumpRelative {
+                condition: Some(condition)
+            },
+            "opcode {opcode:#04X}"
+        );
+        assert_eq!(instruction.cycles(), 3);
+        assert_eq!(instruction.cycles_not_taken(), 2);
+    }
+}
+
+#[test]
+fn jr_nz_branches_on_zero_flag() {
+    // JR NZ,+5
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x20, 0x05], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.pc(), 0x0002);
+
+    cpu.registers_mut().write(register!(PC), 0x0000);
+    cpu.registers_mut().set_flag(Flag::Zero, false);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.pc(), 0x0007);
+}
+
+#[test]
+fn jp_loads_immediate_into_pc() {
+    // JP 0x4000
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xC3, 0x00, 0x40], Address(0x0000))
+        .unwrap();
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.pc(), 0x4000);
+}
+
+#[test]
+fn jp_z_not_taken_skips_operand() {
+    // JP Z,0x4000
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xCA, 0x00, 0x40], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().set_flag(Flag::Zero, false);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.pc(), 0x0003);
+
+    cpu.registers_mut().write(register!(PC), 0x0000);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.pc(), 0x4000);
+}
+
+#[test]
+fn jp_hl_does_not_read_through_hl() {
+    // JP (HL)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xE9], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), 0x8000);
+
+    assert_eq!(cpu.step().unwrap(), 1);
+    assert_eq!(cpu.pc(), 0x8000);
+
+    // Only the opcode fetch
+    assert_eq!(cpu.memory_accesses(), 1);
+    assert!(!Instruction::decode(0xE9).unwrap().accesses_memory());
+}
+
+#[test]
+fn call_pushes_return_address() {
+    // CALL 0x1234
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xCD, 0x34, 0x12], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    assert_eq!(cpu.step().unwrap(), 6);
+
+    assert_eq!(cpu.pc(), 0x1234);
+    assert_eq!(cpu.sp(), 0xFFFC);
+    assert_eq!(cpu.memory().read_byte(Address(0xFFFC)), 0x03);
+    assert_eq!(cpu.memory().read_byte(Address(0xFFFD)), 0x00);
+}
+
+#[test]
+fn call_nc_not_taken_leaves_stack() {
+    // CALL NC,0x1234
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xD4, 0x34, 0x12], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+    cpu.registers_mut().set_flag(Flag::Carry, true);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+
+    assert_eq!(cpu.pc(), 0x0003);
+    assert_eq!(cpu.sp(), 0xFFFE);
+}
+
+#[test]
+fn call_then_ret_round_trips() {
+    // CALL 0x0010; NOP; ... 0x0010: RET
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xCD, 0x10, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(Address(0x0010), 0xC9);
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap(), 4);
+
+    assert_eq!(cpu.pc(), 0x0003);
+    assert_eq!(cpu.sp(), 0xFFFE);
+}
+
+#[test]
+fn ret_cc_costs_depend_on_condition() {
+    // RET Z
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xC8], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFC);
+    cpu.memory_mut()
+        .write(Address(0xFFFC), &[0x00, 0x40])
+        .unwrap();
+
+    cpu.registers_mut().set_flag(Flag::Zero, false);
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.pc(), 0x0001);
+    assert_eq!(cpu.sp(), 0xFFFC);
+
+    cpu.registers_mut().write(register!(PC), 0x0000);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+    assert_eq!(cpu.step().unwrap(), 5);
+    assert_eq!(cpu.pc(), 0x4000);
+    assert_eq!(cpu.sp(), 0xFFFE);
+}
+
+#[test]
+fn reti_enables_interrupts_immediately() {
+    // RETI
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xD9], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFC);
+    cpu.memory_mut()
+        .write(Address(0xFFFC), &[0x00, 0x40])
+        .unwrap();
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.pc(), 0x4000);
+    assert!(cpu.ime());
+}
+
+#[test]
+fn rst_38_pushes_and_jumps_to_vector() {
+    // NOP; RST 0x38
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x00, 0xFF], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap(), 4);
+
+    assert_eq!(cpu.pc(), 0x0038);
+    assert_eq!(cpu.stack_dump(1), vec![0x0002]);
+}
+
+#[test]
+fn rst_vectors_follow_y() {
+    for y in 0..8u8 {
+        let opcode = 0xC7 | (y << 3);
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(*instruction.itype(), InstructionType::Rst(y as u16 * 8));
+        assert_eq!(instruction.length(), 1);
+    }
+}
+
+#[test]
+fn branch_costs_split_only_when_conditional() {
+    // (opcode, taken, not taken)
+    let cases = [
+        (0x18, 3, 3),
+        (0x20, 3, 2),
+        (0xC3, 4, 4),
+        (0xC2, 4, 3),
+        (0xCD, 6, 6),
+        (0xC4, 6, 3),
+        (0xC9, 4, 4),
+        (0xC0, 5, 2),
+        (0x00, 1, 1),
+    ];
+
+    for (opcode, taken, not_taken) in cases {
+        let instruction = Instruction::decode(opcode).unwrap();
+        assert_eq!(instruction.cycles(), taken, "opcode {opcode:#04X}");
+        assert_eq!(
+            instruction.cycles_not_taken(),
+            not_taken,
+            "opcode {opcode:#04X}"
+        );
+    }
+}
+
+#[test]
+fn condition_is_met_table() {
+    // (zero, carry) => NZ, Z, NC, C
+    let table = [
+        ((false, false), [true, false, true, false]),
+        ((true, false), [false, true, true, false]),
+        ((false, true), [true, false, false, true]),
+        ((true, true), [false, true, false, true]),
+    ];
+
+    for ((zero, carry), expected) in table {
+        let mut registers = Registers::default();
+        registers.set_flag(Flag::Zero, zero);
+        registers.set_flag(Flag::Carry, carry);
+        // N and H never matter
+        registers.set_flag(Flag::Sub, true);
+        registers.set_flag(Flag::HalfCarry, true);
+
+        let met = [Condition::NZ, Condition::Z, Condition::NC, Condition::C]
+            .map(|condition| condition.is_met(&registers));
+        assert_eq!(met, expected, "Z={zero} C={carry}");
+    }
+}
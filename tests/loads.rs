@@ -0,0 +1,408 @@
+use gaemboi::cpu::instructions::{DecodeError, FollowUp, Instruction, InstructionType, Operand};
+use gaemboi::cpu::registers::{Flag, Register16, Register8};
+use gaemboi::cpu::{Cpu, Execute};
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+const SENTINEL: u8 = 0x5A;
+const HL_TARGET: u16 = 0xC123;
+
+/// Write `value` into `operand`, for `(HL)` this goes through memory
+fn set_operand(cpu: &mut Cpu, operand: Operand, value: u8) {
+    match operand {
+        Operand::Register8(reg) => cpu.registers_mut().write(reg, value),
+        Operand::Indirect(reg) => {
+            let addr = cpu.registers().fetch(reg);
+            cpu.memory_mut().write_byte(Address(addr), value);
+        }
+        _ => unreachable!("LD r,r only uses the R table"),
+    }
+}
+
+fn get_operand(cpu: &Cpu, operand: Operand) -> u8 {
+    match operand {
+        Operand::Register8(reg) => cpu.registers().fetch(reg),
+        Operand::Indirect(reg) => cpu.memory().read_byte(Address(cpu.registers().fetch(reg))),
+        _ => unreachable!("LD r,r only uses the R table"),
+    }
+}
+
+#[test]
+fn ld_r_r_matrix() {
+    for y in 0..8u8 {
+        for z in 0..8u8 {
+            let opcode = 0b0100_0000 | (y << 3) | z;
+            if opcode == 0x76 {
+                continue;
+            }
+
+            let dst = Operand::from_r_table(y);
+            let src = Operand::from_r_table(z);
+
+            let instruction = Instruction::decode(opcode).unwrap();
+            assert_eq!(
+                *instruction.itype(),
+                InstructionType::Load {
+                    dst,
+                    src,
+                    followup: None
+                },
+                "opcode {opcode:#04X}"
+            );
+
+            let mut cpu = Cpu::default();
+            cpu.registers_mut().write(register!(HL), HL_TARGET);
+            cpu.registers_mut().write(register!(PC), 0x0100);
+            cpu.memory_mut().write_byte(Address(0x0100), opcode);
+
+            set_operand(&mut cpu, src, SENTINEL);
+
+            // Loading H or L into (HL) moves the target address with it
+            let expected = get_operand(&cpu, src);
+
+            cpu.step().unwrap();
+
+            assert_eq!(get_operand(&cpu, dst), expected, "opcode {opcode:#04X}");
+            assert_eq!(cpu.registers().fetch(register!(PC)), 0x0101);
+        }
+    }
+}
+
+#[test]
+fn ld_a_a16_accesses_match_cycles() {
+    let mut cpu = Cpu::default();
+    // LD A,(0xC010)
+    cpu.load_rom_with_entry(&[0xFA, 0x10, 0xC0], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(Address(0xC010), 0x3C);
+
+    let cycles = cpu.step().unwrap();
+
+    // Opcode, two address bytes and the data read
+    assert_eq!(cpu.memory_accesses(), 4);
+    assert_eq!(cycles, 4);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x3C);
+}
+
+#[test]
+fn hl_indirect_loads_have_own_entries() {
+    let hl = Operand::Indirect(register!(HL));
+
+    let stores = (0x70..=0x77).filter(|&op| op != 0x76);
+    let loads = (0..8u8).filter(|&y| y != 6).map(|y| 0x46 | (y << 3));
+
+    for opcode in stores.chain(loads) {
+        let instruction = Instruction::decode(opcode).unwrap();
+        let InstructionType::Load { dst, src, .. } = *instruction.itype() else {
+            panic!("opcode {opcode:#04X} is not a load");
+        };
+
+        assert!(dst == hl || src == hl, "opcode {opcode:#04X}");
+        assert!(instruction.accesses_memory(), "opcode {opcode:#04X}");
+        assert_eq!(instruction.cycles(), 2, "opcode {opcode:#04X}");
+    }
+
+    let register_load = Instruction::decode(0x41).unwrap();
+    assert!(!register_load.accesses_memory());
+    assert_eq!(register_load.cycles(), 1);
+}
+
+#[test]
+fn hl_increment_and_decrement_stores() {
+    // LD (HL+),A; LD (HL-),A
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x22, 0x32], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(HL), HL_TARGET);
+    cpu.registers_mut().write(register!(A), SENTINEL);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.memory().read_byte(Address(HL_TARGET)), SENTINEL);
+    assert_eq!(cpu.registers().fetch(register!(HL)), HL_TARGET + 1);
+
+    cpu.registers_mut().write(register!(A), !SENTINEL);
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.memory().read_byte(Address(HL_TARGET + 1)), !SENTINEL);
+    assert_eq!(cpu.registers().fetch(register!(HL)), HL_TARGET);
+}
+
+#[test]
+fn hl_decrement_load_into_a() {
+    let instruction = Instruction::decode(0x3A).unwrap();
+    assert_eq!(
+        *instruction.itype(),
+        InstructionType::Load {
+            dst: Operand::Register8(register!(A)),
+            src: Operand::Indirect(register!(HL)),
+            followup: Some(FollowUp::Dec)
+        }
+    );
+
+    // LD A,(HL-)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x3A], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), HL_TARGET);
+    cpu.memory_mut().write_byte(Address(HL_TARGET), SENTINEL);
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.registers().fetch(register!(A)), SENTINEL);
+    assert_eq!(cpu.registers().fetch(register!(HL)), HL_TARGET - 1);
+}
+
+#[test]
+fn absolute_store_and_load_round_trip() {
+    // LD (0xC010),A; LD A,(0xC011)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xEA, 0x10, 0xC0, 0xFA, 0x11, 0xC0], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), SENTINEL);
+    cpu.memory_mut().write_byte(Address(0xC011), !SENTINEL);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.memory().read_byte(Address(0xC010)), SENTINEL);
+    assert_eq!(cpu.pc(), 0x0003);
+
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.registers().fetch(register!(A)), !SENTINEL);
+    assert_eq!(cpu.pc(), 0x0006);
+}
+
+#[test]
+fn ldh_addresses_high_page() {
+    // LDH (0x80),A; LD A,(C)
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xE0, 0x80, 0xF2], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), SENTINEL);
+    cpu.registers_mut().write(register!(C), 0x84);
+    cpu.memory_mut().write_byte(Address(0xFF84), 0x90);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.memory().read_byte(Address(0xFF80)), SENTINEL);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x90);
+    assert_eq!(cpu.pc(), 0x0003);
+}
+
+#[test]
+fn ldh_loads_into_a_and_stores_through_c() {
+    // LDH A,(0x80); LD (C),A
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xF0, 0x80, 0xE2], Address(0x0000))
+        .unwrap();
+    cpu.memory_mut().write_byte(Address(0xFF80), SENTINEL);
+    cpu.registers_mut().write(register!(C), 0x81);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.memory().read_byte(Address(0xFF81)), SENTINEL);
+}
+
+#[test]
+fn ld_rp_immediate() {
+    // LD HL,0xBEEF
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x21, 0xEF, 0xBE], Address(0x0000))
+        .unwrap();
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.registers().fetch(register!(HL)), 0xBEEF);
+    assert_eq!(cpu.pc(), 0x0003);
+
+    for (opcode, dst) in [
+        (0x01, register!(BC)),
+        (0x11, register!(DE)),
+        (0x31, register!(SP)),
+    ] {
+        assert_eq!(
+            *Instruction::decode(opcode).unwrap().itype(),
+            InstructionType::Load {
+                dst: Operand::Register16(dst),
+                src: Operand::Immediate16,
+                followup: None
+            }
+        );
+    }
+}
+
+#[test]
+fn store_sp_low_byte_first() {
+    // LD (0xC000),SP
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0x08, 0x00, 0xC0], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    assert_eq!(cpu.step().unwrap(), 5);
+
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0xFE);
+    assert_eq!(cpu.memory().read_byte(Address(0xC001)), 0xFF);
+    assert_eq!(cpu.pc(), 0x0003);
+}
+
+#[test]
+fn ld_sp_hl_leaves_flags() {
+    // LD SP,HL
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xF9], Address(0x0000)).unwrap();
+    cpu.registers_mut().write(register!(HL), 0xDFF0);
+    cpu.registers_mut().write(register!(F), 0xB0);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.sp(), 0xDFF0);
+    assert_eq!(cpu.registers().fetch(register!(F)), 0xB0);
+}
+
+#[test]
+fn ld_hl_sp_offset_carries_from_low_byte() {
+    // LD HL,SP+8
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xF8, 0x08], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xFFF8);
+    cpu.registers_mut().set_flag(Flag::Zero, true);
+    cpu.registers_mut().set_flag(Flag::Sub, true);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+
+    assert_eq!(cpu.registers().fetch(register!(HL)), 0x0000);
+    assert_eq!(cpu.sp(), 0xFFF8);
+    assert!(!cpu.registers().flag(Flag::Zero));
+    assert!(!cpu.registers().flag(Flag::Sub));
+    assert!(cpu.registers().flag(Flag::HalfCarry));
+    assert!(cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn ld_hl_sp_negative_offset() {
+    // LD HL,SP-1
+    let mut cpu = Cpu::default();
+    cpu.load_rom_with_entry(&[0xF8, 0xFF], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0x0100);
+
+    cpu.step().unwrap();
+
+    // 0x00 + 0xFF carries out of neither nibble nor byte
+    assert_eq!(cpu.registers().fetch(register!(HL)), 0x00FF);
+    assert!(!cpu.registers().flag(Flag::HalfCarry));
+    assert!(!cpu.registers().flag(Flag::Carry));
+}
+
+#[test]
+fn ld_r_d8_loads_immediate() {
+    let mut cpu = Cpu::default();
+    // LD B,0x12; LD (HL),0x34; LD A,0x56
+    cpu.load_rom_with_entry(&[0x06, 0x12, 0x36, 0x34, 0x3E, 0x56], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x12);
+
+    assert_eq!(cpu.step().unwrap(), 3);
+    assert_eq!(cpu.memory().read_byte(Address(0xC000)), 0x34);
+
+    assert_eq!(cpu.step().unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x56);
+    assert_eq!(cpu.pc(), 0x0006);
+}
+
+#[test]
+fn try_load_rejects_nonsensical_operands() {
+    let invalid = [
+        (Operand::Immediate8, Operand::Register8(Register8::A)),
+        (Operand::Immediate16, Operand::Register16(Register16::HL)),
+        (Operand::Register8(Register8::B), Operand::Immediate16),
+        (
+            Operand::Register8(Register8::B),
+            Operand::Register16(Register16::BC),
+        ),
+        (
+            Operand::Register16(Register16::BC),
+            Operand::Register8(Register8::B),
+        ),
+        (Operand::Register16(Register16::SP), Operand::Immediate8),
+        (
+            Operand::Indirect(Register16::HL),
+            Operand::Indirect(Register16::BC),
+        ),
+        (Operand::IndirectImmediate16, Operand::HighC),
+    ];
+    for (dst, src) in invalid {
+        assert_eq!(
+            Instruction::try_load(dst, src),
+            Err(DecodeError::InvalidLoadOperands { dst, src }),
+            "LD {dst},{src}"
+        );
+    }
+
+    let valid = [
+        (Operand::Register8(Register8::A), Operand::Immediate8),
+        (Operand::Indirect(Register16::HL), Operand::Immediate8),
+        (
+            Operand::Register16(Register16::SP),
+            Operand::Register16(Register16::HL),
+        ),
+        (Operand::Register16(Register16::DE), Operand::Immediate16),
+        (Operand::HighImmediate8, Operand::Register8(Register8::A)),
+        (
+            Operand::Register8(Register8::A),
+            Operand::IndirectImmediate16,
+        ),
+    ];
+    for (dst, src) in valid {
+        assert_eq!(
+            Instruction::try_load(dst, src),
+            Ok(Instruction::load(dst, src))
+        );
+    }
+}
+
+#[test]
+fn decoded_loads_pass_validation() {
+    for opcode in 0..=255 {
+        // The table is built through try_load, so a bad operand pair would
+        // surface here rather than only tripping a debug assertion
+        let instruction = match Instruction::decode(opcode) {
+            Ok(instruction) => instruction,
+            Err(err) => {
+                assert!(
+                    matches!(err, DecodeError::UnknownOpcode { .. }),
+                    "{opcode:#04X}: {err}"
+                );
+                continue;
+            }
+        };
+        if let InstructionType::Load { dst, src, .. } = *instruction.itype() {
+            assert!(Instruction::try_load(dst, src).is_ok(), "{opcode:#04X}");
+        }
+    }
+}
+
+#[test]
+fn load_routes_by_destination_width() {
+    let mut cpu = Cpu::default();
+    cpu.registers_mut().write(register!(HL), 0xBEEF);
+    cpu.registers_mut().write(register!(C), 0x42);
+
+    // No opcode moves HL into DE, but a hand-built word load still runs
+    let word = Instruction::try_load(
+        Operand::Register16(Register16::DE),
+        Operand::Register16(Register16::HL),
+    )
+    .unwrap();
+    assert_eq!(cpu.execute(word).unwrap(), 2);
+    assert_eq!(cpu.registers().fetch(register!(DE)), 0xBEEF);
+
+    let byte = Instruction::try_load(
+        Operand::Register8(Register8::E),
+        Operand::Register8(Register8::C),
+    )
+    .unwrap();
+    assert_eq!(cpu.execute(byte).unwrap(), 1);
+    assert_eq!(cpu.registers().fetch(register!(DE)), 0xBE42);
+}
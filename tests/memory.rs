@@ -0,0 +1,141 @@
+use gaemboi::joypad::P1;
+use gaemboi::memory::{Address, Memory, ReadError, WriteError};
+use gaemboi::ppu::LCDC;
+use gaemboi::timer::TAC;
+
+#[test]
+fn read_past_end_is_an_error_not_a_panic() {
+    let memory = Memory::default();
+
+    assert_eq!(memory.read(Address(0xFFF0), 0x10).unwrap().len(), 0x10);
+    assert_eq!(
+        memory.read(Address(0xFFF0), 0x11),
+        Err(ReadError::MemoryOverflow {
+            start: 0xFFF0,
+            len: 0x11
+        })
+    );
+    assert_eq!(
+        memory.read(Address(0x0001), usize::MAX),
+        Err(ReadError::MemoryOverflow {
+            start: 0x0001,
+            len: usize::MAX
+        })
+    );
+}
+
+#[test]
+fn last_address_is_backed() {
+    let mut memory = Memory::default();
+
+    // The interrupt enable register lives at the very top
+    memory.write_byte(Address(0xFFFF), 0x1F);
+    assert_eq!(memory.read_byte(Address(0xFFFF)), 0x1F);
+
+    memory.write(Address(0xFFFF), &[0x04]).unwrap();
+    assert_eq!(memory.read(Address(0xFFFF), 1).unwrap(), &[0x04]);
+}
+
+#[test]
+fn address_arithmetic_wraps() {
+    assert_eq!(Address(0xFFFF) + 1, Address(0x0000));
+    assert_eq!(Address(0x0000) - 1, Address(0xFFFF));
+    assert_eq!(Address(0xC000) + 0x10, Address(0xC010));
+
+    assert_eq!(Address(0x0105).offset(-3), Address(0x0102));
+    assert_eq!(Address(0x0001).offset(-2), Address(0xFFFF));
+    assert_eq!(Address(0xFFFE).offset(127), Address(0x007D));
+}
+
+#[test]
+fn words_are_little_endian_and_wrap() {
+    let mut memory = Memory::default();
+
+    memory.write_word(Address(0xC000), 0xBEEF);
+    assert_eq!(memory.read(Address(0xC000), 2).unwrap(), &[0xEF, 0xBE]);
+    assert_eq!(memory.read_word(Address(0xC000)), 0xBEEF);
+
+    // The high byte lands back at the bottom of memory
+    memory.write_word(Address(0xFFFF), 0x1234);
+    assert_eq!(memory.read_byte(Address(0xFFFF)), 0x34);
+    assert_eq!(memory.read_byte(Address(0x0000)), 0x12);
+    assert_eq!(memory.read_word(Address(0xFFFF)), 0x1234);
+}
+
+#[test]
+fn io_register_writes_reach_their_component() {
+    let mut memory = Memory::default();
+
+    memory.write_byte(TAC, 0x05);
+    memory.write_byte(LCDC, 0x80);
+    memory.write_byte(P1, 0x20);
+
+    // Each handler reports its own view, unused bits and all
+    assert_eq!(memory.read_byte(TAC), 0xFD);
+    assert_eq!(memory.read_byte(LCDC), 0x80);
+    assert!(memory.ppu().is_enabled());
+    assert_eq!(memory.read_byte(P1), 0xEF);
+
+    // Nothing lands in the flat backing memory
+    for reg in [TAC, LCDC, P1] {
+        assert_eq!(memory.read(reg, 1).unwrap(), &[0x00]);
+    }
+
+    // Work RAM and high RAM are plain memory
+    memory.write_byte(Address(0xC000), 0x11);
+    memory.write_byte(Address(0xFF80), 0x22);
+    assert_eq!(memory.read(Address(0xC000), 1).unwrap(), &[0x11]);
+    assert_eq!(memory.read(Address(0xFF80), 1).unwrap(), &[0x22]);
+}
+
+#[test]
+fn load_at_and_fill_set_up_regions() {
+    let mut memory = Memory::default();
+
+    memory.fill(Address(0xC000)..=Address(0xC0FF), 0xAA);
+    memory.load_at(Address(0xC010), &[1, 2, 3]).unwrap();
+
+    assert_eq!(memory.read_byte(Address(0xBFFF)), 0x00);
+    assert_eq!(
+        memory.read(Address(0xC00F), 5).unwrap(),
+        &[0xAA, 1, 2, 3, 0xAA]
+    );
+    assert_eq!(memory.read_byte(Address(0xC0FF)), 0xAA);
+    assert_eq!(memory.read_byte(Address(0xC100)), 0x00);
+
+    // The whole space, top address included
+    memory.fill(Address(0x0000)..=Address(0xFFFF), 0x11);
+    assert_eq!(memory.read_byte(Address(0xFFFF)), 0x11);
+
+    assert_eq!(
+        memory.load_at(Address(0xFFFE), &[1, 2, 3]),
+        Err(WriteError::MemoryOverflow {
+            start: 0xFFFE,
+            len: 3
+        })
+    );
+    assert_eq!(memory.read_byte(Address(0xFFFE)), 0x11);
+}
+
+#[test]
+fn read_owned_allows_writing_while_held() {
+    let mut memory = Memory::default();
+    let pattern: Vec<u8> = (0..16).collect();
+    memory.load_at(Address(0xC000), &pattern).unwrap();
+
+    // DMA style: copy out, then write the copy elsewhere
+    let buffer = memory.read_owned(Address(0xC000), 16).unwrap();
+    memory.write(Address(0xFE00), &buffer).unwrap();
+    memory.write_byte(Address(0xC000), 0xFF);
+
+    assert_eq!(buffer, pattern);
+    assert_eq!(memory.read(Address(0xFE00), 16).unwrap(), &pattern[..]);
+
+    assert_eq!(
+        memory.read_owned(Address(0xFFF8), 16),
+        Err(ReadError::MemoryOverflow {
+            start: 0xFFF8,
+            len: 16
+        })
+    );
+}
@@ -0,0 +1,157 @@
+use gaemboi::cpu::interrupts::INTERRUPT_FLAG;
+use gaemboi::cpu::{Cpu, CYCLES_PER_FRAME};
+use gaemboi::memory::{Address, Memory};
+use gaemboi::ppu::stat::LYC_SOURCE;
+use gaemboi::ppu::{Mode, BGP, LCDC, LY, LYC, SCREEN_WIDTH, SCX, STAT};
+
+fn lcd_on() -> Memory {
+    let mut memory = Memory::default();
+    memory.write_byte(LCDC, 0x80);
+    memory
+}
+
+#[test]
+fn frame_cycles_ly_and_raises_vblank_once() {
+    let mut memory = lcd_on();
+    let mut lines = vec![memory.read_byte(LY)];
+    let mut vblanks = 0;
+
+    for _ in 0..CYCLES_PER_FRAME {
+        memory.tick(1);
+
+        let ly = memory.read_byte(LY);
+        if lines.last() != Some(&ly) {
+            lines.push(ly);
+        }
+
+        if memory.read_byte(INTERRUPT_FLAG) & 1 != 0 {
+            vblanks += 1;
+            assert_eq!(ly, 144);
+            memory.write_byte(INTERRUPT_FLAG, 0);
+        }
+    }
+
+    assert_eq!(lines, (0..=153).chain([0]).collect::<Vec<u8>>());
+    assert_eq!(vblanks, 1);
+}
+
+#[test]
+fn visible_line_runs_through_modes() {
+    let mut memory = lcd_on();
+    let mode = |memory: &Memory| memory.ppu().mode();
+
+    assert_eq!(mode(&memory), Mode::OamSearch);
+    assert_eq!(memory.read_byte(STAT) & 0b11, 2);
+
+    memory.tick(20);
+    assert_eq!(mode(&memory), Mode::PixelTransfer);
+
+    memory.tick(43);
+    assert_eq!(mode(&memory), Mode::HBlank);
+
+    memory.tick(51);
+    assert_eq!(mode(&memory), Mode::OamSearch);
+    assert_eq!(memory.read_byte(LY), 1);
+
+    memory.tick(114 * 143);
+    assert_eq!(mode(&memory), Mode::VBlank);
+    assert_eq!(memory.read_byte(STAT) & 0b11, 1);
+}
+
+#[test]
+fn lcd_off_holds_ly_at_zero() {
+    let mut memory = lcd_on();
+    memory.tick(114 * 10);
+    assert_eq!(memory.read_byte(LY), 10);
+
+    memory.write_byte(LCDC, 0x00);
+    memory.tick(CYCLES_PER_FRAME);
+    assert_eq!(memory.read_byte(LY), 0);
+    assert_eq!(memory.ppu().mode(), Mode::HBlank);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 0);
+}
+
+#[test]
+fn lyc_match_requests_stat() {
+    let mut memory = lcd_on();
+    memory.write_byte(LYC, 3);
+    memory.write_byte(STAT, LYC_SOURCE);
+
+    memory.tick(114 * 3);
+    assert_eq!(memory.read_byte(LY), 3);
+    assert_eq!(memory.read_byte(STAT) & 0b100, 0b100);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 1 << 1);
+}
+
+#[test]
+fn long_tick_matches_short_ticks() {
+    let mut long = lcd_on();
+    let mut short = lcd_on();
+
+    let total = CYCLES_PER_FRAME * 5 + 1234;
+    long.tick(total);
+    for _ in 0..total / 100 {
+        short.tick(100);
+    }
+    short.tick(total % 100);
+
+    assert_eq!(long.ppu(), short.ppu());
+    assert_eq!(
+        long.read_byte(INTERRUPT_FLAG),
+        short.read_byte(INTERRUPT_FLAG)
+    );
+}
+
+/// Every row reads colours 0, 1, 2, 3, 0, 1, 2, 3 left to right
+const STRIPES: [u8; 16] = [
+    0x55, 0x33, 0x55, 0x33, 0x55, 0x33, 0x55, 0x33, 0x55, 0x33, 0x55, 0x33, 0x55, 0x33, 0x55, 0x33,
+];
+
+/// The first line's pixel transfer
+const TO_FIRST_HBLANK: u64 = 63;
+
+#[test]
+fn background_tile_goes_through_palette() {
+    let mut cpu = Cpu::default();
+    cpu.memory_mut().write(Address(0x8010), &STRIPES).unwrap();
+    cpu.memory_mut().write_byte(Address(0x9800), 1);
+    cpu.memory_mut().write_byte(BGP, 0b11_10_01_00);
+    // LCD and background on, tile data at 0x8000, map at 0x9800
+    cpu.memory_mut().write_byte(LCDC, 0x91);
+
+    cpu.memory_mut().tick(TO_FIRST_HBLANK);
+    assert_eq!(cpu.framebuffer()[..10], [0, 1, 2, 3, 0, 1, 2, 3, 0, 0]);
+
+    // The palette remaps colours, and SCX scrolls the next line left
+    cpu.memory_mut().write_byte(BGP, 0b00_01_10_11);
+    cpu.memory_mut().write_byte(SCX, 2);
+    cpu.memory_mut().tick(114);
+    let line = &cpu.framebuffer()[SCREEN_WIDTH..];
+    assert_eq!(line[..8], [1, 0, 3, 2, 1, 0, 3, 3]);
+}
+
+#[test]
+fn signed_tile_data_and_high_map() {
+    let mut memory = Memory::default();
+    // Tile -1 counts back from 0x9000
+    memory.write(Address(0x8FF0), &STRIPES).unwrap();
+    memory.write_byte(Address(0x9C00), 0xFF);
+    memory.write_byte(BGP, 0b11_10_01_00);
+    memory.write_byte(LCDC, 0x89);
+
+    memory.tick(TO_FIRST_HBLANK);
+    assert_eq!(memory.ppu().framebuffer()[..4], [0, 1, 2, 3]);
+}
+
+#[test]
+fn background_disabled_draws_blank() {
+    let mut memory = Memory::default();
+    memory.write(Address(0x8000), &STRIPES).unwrap();
+    memory.write_byte(BGP, 0b11_10_01_00);
+    memory.write_byte(LCDC, 0x90);
+
+    memory.tick(TO_FIRST_HBLANK);
+    assert!(memory.ppu().framebuffer()[..SCREEN_WIDTH]
+        .iter()
+        .all(|&shade| shade == 0));
+}
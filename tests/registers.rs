@@ -0,0 +1,102 @@
+use gaemboi::cpu::registers::{Flag, Registers};
+use gaemboi::cpu::Cpu;
+use gaemboi::register;
+
+#[test]
+fn flag_bits_match_hardware_layout() {
+    assert_eq!(Flag::Zero.bit(), 0x80);
+    assert_eq!(Flag::Sub.bit(), 0x40);
+    assert_eq!(Flag::HalfCarry.bit(), 0x20);
+    assert_eq!(Flag::Carry.bit(), 0x10);
+}
+
+#[test]
+fn set_flag_matches_raw_mask() {
+    let mut via_enum = Registers::default();
+    via_enum.set_flag(Flag::Zero, true);
+    via_enum.set_flag(Flag::Carry, true);
+
+    let mut via_mask = Registers::default();
+    via_mask.write(register!(F), 0x80 | 0x10);
+
+    assert_eq!(via_enum, via_mask);
+    assert!(via_enum.flag(Flag::Zero));
+    assert!(!via_enum.flag(Flag::Sub));
+
+    via_enum.set_flag(Flag::Zero, false);
+    assert_eq!(via_enum.fetch(register!(F)), 0x10);
+}
+
+#[test]
+fn all_flags_fill_upper_nibble_only() {
+    let mut registers = Registers::default();
+    for flag in [Flag::Zero, Flag::Sub, Flag::HalfCarry, Flag::Carry] {
+        registers.set_flag(flag, true);
+    }
+    assert_eq!(registers.fetch(register!(F)), 0xF0);
+
+    // The unused low bits never stick
+    registers.write(register!(F), 0xFF);
+    assert_eq!(registers.fetch(register!(F)), 0xF0);
+}
+
+#[test]
+fn af_writes_and_flags_agree() {
+    let mut registers = Registers::default();
+    registers.write(register!(AF), 0x12A5);
+
+    assert_eq!(registers.fetch(register!(AF)), 0x12A0);
+    assert!(registers.flag(Flag::Zero));
+    assert!(!registers.flag(Flag::Sub));
+    assert!(registers.flag(Flag::HalfCarry));
+    assert!(!registers.flag(Flag::Carry));
+
+    registers.set_flag(Flag::Carry, true);
+    assert_eq!(registers.fetch(register!(AF)), 0x12B0);
+}
+
+#[test]
+fn inc_and_dec_wrap() {
+    let mut registers = Registers::default();
+
+    registers.write(register!(HL), 0xFFFF);
+    registers.inc(register!(HL));
+    assert_eq!(registers.fetch(register!(HL)), 0x0000);
+
+    registers.dec(register!(SP));
+    assert_eq!(registers.fetch(register!(SP)), 0xFFFF);
+
+    registers.write(register!(B), 0xFF);
+    registers.inc(register!(B));
+    assert_eq!(registers.fetch(register!(B)), 0x00);
+
+    registers.dec(register!(C));
+    assert_eq!(registers.fetch(register!(C)), 0xFF);
+}
+
+#[test]
+fn debug_shows_hex_pairs_and_flags() {
+    let mut registers = Registers::default();
+    registers.write(register!(AF), 0x01B0);
+    registers.write(register!(BC), 0x0013);
+    registers.write(register!(DE), 0x00D8);
+    registers.write(register!(HL), 0x014D);
+    registers.write(register!(SP), 0xFFFE);
+    registers.write(register!(PC), 0x0100);
+
+    assert_eq!(
+        format!("{registers:?}"),
+        "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D \
+         AF:01B0 BC:0013 DE:00D8 HL:014D SP:FFFE PC:0100 [Z-HC]"
+    );
+
+    registers.write(register!(F), 0x40);
+    assert!(format!("{registers:?}").ends_with("[-N--]"));
+}
+
+#[test]
+fn cpu_uses_the_canonical_registers_type() {
+    let cpu = Cpu::default();
+    let registers: &Registers = cpu.registers();
+    assert_eq!(registers, &Registers::default());
+}
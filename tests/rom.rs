@@ -0,0 +1,74 @@
+use gaemboi::cpu::instructions::Instruction;
+use gaemboi::cpu::{Cpu, CpuError};
+use gaemboi::memory::{Address, ROM_SIZE};
+use gaemboi::register;
+
+#[test]
+fn raw_blob_runs_from_custom_entry() {
+    let mut cpu = Cpu::default();
+    cpu.registers_mut().write(register!(B), 0x42);
+
+    // LD A,B; LD C,A
+    cpu.load_rom_with_entry(&[0x78, 0x4F], Address(0x0000))
+        .unwrap();
+    assert_eq!(cpu.registers().fetch(register!(PC)), 0x0000);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x42);
+    assert_eq!(cpu.registers().fetch(register!(PC)), 0x0001);
+}
+
+#[test]
+fn load_rom_places_bytes_at_zero() {
+    let mut cpu = Cpu::default();
+    // LD A,B
+    cpu.load_rom(&[0x78]).unwrap();
+    cpu.registers_mut().write(register!(B), 0x42);
+
+    assert_eq!(cpu.pc(), 0x0000);
+    assert_eq!(
+        Instruction::decode(cpu.memory().read_byte(Address(0x0000))).unwrap(),
+        Instruction::decode(0x78).unwrap()
+    );
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x42);
+}
+
+#[test]
+fn load_rom_rejects_oversized_image() {
+    let mut cpu = Cpu::default();
+
+    let err = cpu.load_rom(&vec![0; ROM_SIZE + 1]).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::RomTooLarge(ROM_SIZE + 1))
+    );
+
+    // Nor can a raw payload with its own entry point spill into VRAM
+    let err = cpu
+        .load_rom_with_entry(&vec![0x3C; ROM_SIZE + 1], Address(0x0150))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::RomTooLarge(ROM_SIZE + 1))
+    );
+    assert_eq!(cpu.memory().read_byte(Address(0x8000)), 0x00);
+    assert_eq!(cpu.pc(), 0x0000);
+}
+
+#[test]
+fn from_rom_path_reads_file() {
+    let path = std::env::temp_dir().join(format!("gaemboi-rom-{}.gb", std::process::id()));
+    // INC A
+    std::fs::write(&path, [0x3C]).unwrap();
+
+    let mut cpu = Cpu::from_rom_path(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x01);
+
+    assert!(Cpu::from_rom_path(&path).is_err());
+}
@@ -0,0 +1,54 @@
+use gaemboi::cpu::interrupts::INTERRUPT_FLAG;
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+use gaemboi::serial::{SB, SC};
+
+#[test]
+fn serial_output_collects_sent_bytes() {
+    let mut cpu = Cpu::default();
+
+    for &byte in b"Passed" {
+        cpu.memory_mut().write_byte(SB, byte);
+        cpu.memory_mut().write_byte(SC, 0x81);
+    }
+
+    assert_eq!(cpu.serial_output(), "Passed");
+
+    // Nothing is connected, and the transfer has already finished
+    assert_eq!(cpu.memory().read_byte(SB), 0xFF);
+    assert_eq!(cpu.memory().read_byte(SC), 0x01);
+    assert_eq!(cpu.memory().read_byte(INTERRUPT_FLAG), 1 << 3);
+}
+
+#[test]
+fn only_internal_clock_start_sends() {
+    let mut cpu = Cpu::default();
+    cpu.memory_mut().write_byte(SB, b'x');
+    cpu.memory_mut().write_byte(SC, 0x80);
+    cpu.memory_mut().write_byte(SC, 0x01);
+
+    assert_eq!(cpu.serial_output(), "");
+}
+
+#[test]
+fn program_prints_through_serial() {
+    let mut cpu = Cpu::default();
+    // LD A,(HL+); LDH (0x01),A; LD A,B; LDH (0x02),A; LD A,(HL+); LDH (0x01),A; LD A,B; LDH (0x02),A
+    cpu.load_rom_with_entry(
+        &[
+            0x2A, 0xE0, 0x01, 0x78, 0xE0, 0x02, 0x2A, 0xE0, 0x01, 0x78, 0xE0, 0x02,
+        ],
+        Address(0x0000),
+    )
+    .unwrap();
+    cpu.memory_mut().write(Address(0xC000), b"ok").unwrap();
+    cpu.registers_mut().write(register!(HL), 0xC000);
+    cpu.registers_mut().write(register!(B), 0x81);
+
+    for _ in 0..8 {
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(cpu.serial_output(), "ok");
+}
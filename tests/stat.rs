@@ -0,0 +1,41 @@
+use gaemboi::ppu::stat::{StatLine, HBLANK_SOURCE, LYC_SOURCE, OAM_SOURCE, VBLANK_SOURCE};
+use gaemboi::ppu::Mode;
+
+#[test]
+fn each_enabled_source_triggers() {
+    let cases = [
+        (HBLANK_SOURCE, Mode::HBlank),
+        (VBLANK_SOURCE, Mode::VBlank),
+        (OAM_SOURCE, Mode::OamSearch),
+    ];
+
+    for (source, mode) in cases {
+        let mut line = StatLine::default();
+        assert!(!line.update(source, Mode::PixelTransfer, 0, 1));
+        assert!(line.update(source, mode, 0, 1), "{mode:?}");
+
+        // Only sources whose enable bit is set drive the line
+        let mut line = StatLine::default();
+        assert!(!line.update(!source & 0x78, mode, 0, 1), "{mode:?}");
+    }
+
+    let mut line = StatLine::default();
+    assert!(!line.update(LYC_SOURCE, Mode::PixelTransfer, 4, 5));
+    assert!(line.update(LYC_SOURCE, Mode::PixelTransfer, 5, 5));
+}
+
+#[test]
+fn line_held_high_blocks_retrigger() {
+    let stat = LYC_SOURCE | HBLANK_SOURCE;
+    let mut line = StatLine::default();
+
+    assert!(line.update(stat, Mode::PixelTransfer, 10, 10));
+
+    // HBlank begins while the coincidence is still holding the line high
+    assert!(!line.update(stat, Mode::HBlank, 10, 10));
+    assert!(line.is_high());
+
+    // Once every source drops the next one triggers again
+    assert!(!line.update(stat, Mode::OamSearch, 11, 10));
+    assert!(line.update(stat, Mode::HBlank, 11, 10));
+}
@@ -0,0 +1,195 @@
+use gaemboi::cpu::interrupts::INTERRUPT_ENABLE;
+use gaemboi::cpu::state::{StateError, VERSION};
+use gaemboi::cpu::{Cpu, CpuError};
+use gaemboi::joypad::{Button, P1};
+use gaemboi::memory::Address;
+use gaemboi::register;
+use gaemboi::timer::TAC;
+
+/// A CPU a few instructions into a program with non-default state
+fn running_cpu() -> Cpu {
+    let mut cpu = Cpu::default();
+    // LD B,A; PUSH BC; EI; NOP; ...
+    cpu.load_rom_with_entry(&[0x47, 0xC5, 0xFB, 0x00, 0x00, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(A), 0x12);
+    cpu.registers_mut().write(register!(SP), 0xDFFE);
+
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn save_state_round_trip() {
+    let mut cpu = running_cpu();
+    let saved = cpu.save_state();
+    let snapshot = cpu.state();
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.memory_mut().write_byte(Address(0xC000), 0xAB);
+    assert_ne!(cpu.state(), snapshot);
+
+    cpu.load_state(&saved).unwrap();
+    assert_eq!(cpu.state(), snapshot);
+
+    // Resuming reproduces the same execution
+    let mut reference = running_cpu();
+    cpu.step().unwrap();
+    reference.step().unwrap();
+    assert_eq!(cpu.state(), reference.state());
+}
+
+#[test]
+fn load_state_rejects_other_versions() {
+    let mut cpu = running_cpu();
+    let mut saved = cpu.save_state();
+    saved[4] = VERSION + 1;
+
+    let before = cpu.state();
+    let err = cpu.load_state(&saved).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<StateError>(),
+        Some(&StateError::UnsupportedVersion(VERSION + 1))
+    );
+    assert_eq!(cpu.state(), before);
+
+    assert_eq!(
+        cpu.load_state(b"nope")
+            .unwrap_err()
+            .downcast_ref::<StateError>(),
+        Some(&StateError::BadMagic)
+    );
+}
+
+#[test]
+fn rewind_restores_captured_frames() {
+    let mut cpu = Cpu::default();
+    // JR -2
+    cpu.load_rom_with_entry(&[0x18, 0xFE], Address(0x0000))
+        .unwrap();
+    cpu.enable_rewind(4, 2);
+
+    let mut snapshots = Vec::new();
+    for frame in 1..=4 {
+        cpu.run_frame().unwrap();
+        if frame % 2 == 0 {
+            snapshots.push(cpu.state());
+        }
+    }
+
+    cpu.run_frame().unwrap();
+    assert_ne!(cpu.state(), snapshots[1]);
+
+    cpu.rewind().unwrap();
+    assert_eq!(cpu.state(), snapshots[1]);
+
+    cpu.rewind().unwrap();
+    assert_eq!(cpu.state(), snapshots[0]);
+
+    let err = cpu.rewind().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::NothingToRewind)
+    );
+}
+
+#[test]
+fn restored_state_replays_identically_through_halt() {
+    let mut cpu = Cpu::default();
+    // EI; HALT; INC A; JR -3, with the timer interrupt waking HALT
+    cpu.load_rom_with_entry(&[0xFB, 0x76, 0x3C, 0x18, 0xFC], Address(0x0000))
+        .unwrap();
+    // 0x0050: RETI
+    cpu.memory_mut().write_byte(Address(0x0050), 0xD9);
+    cpu.registers_mut().write(register!(SP), 0xDFFE);
+    cpu.memory_mut().write_byte(INTERRUPT_ENABLE, 1 << 2);
+    cpu.memory_mut().write_byte(TAC, 0b101);
+
+    cpu.run_for_cycles(100).unwrap();
+    let saved = cpu.save_state();
+
+    cpu.run_for_cycles(2000).unwrap();
+    let first = cpu.state();
+    assert!(cpu.registers().fetch(register!(A)) > 0);
+
+    cpu.load_state(&saved).unwrap();
+    cpu.run_for_cycles(2000).unwrap();
+    assert_eq!(cpu.state(), first);
+}
+
+#[test]
+fn step_back_undoes_steps() {
+    let mut cpu = Cpu::default();
+    // INC A; LD B,A; PUSH BC; NOP
+    cpu.load_rom_with_entry(&[0x3C, 0x47, 0xC5, 0x00], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(SP), 0xDFFE);
+    cpu.enable_step_history(2);
+
+    cpu.step().unwrap();
+    let after_first = cpu.state();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0003);
+    assert_eq!(cpu.sp(), 0xDFFC);
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.pc(), 0x0002);
+    assert_eq!(cpu.sp(), 0xDFFE);
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x01);
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.state(), after_first);
+    assert_eq!(cpu.registers().fetch(register!(B)), 0x00);
+
+    // Only two steps were kept
+    let err = cpu.step_back().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CpuError>(),
+        Some(&CpuError::NothingToRewind)
+    );
+}
+
+#[test]
+fn step_back_covers_step_instruction() {
+    let mut cpu = Cpu::default();
+    // INC A; INC A; INC A
+    cpu.load_rom_with_entry(&[0x3C, 0x3C, 0x3C], Address(0x0000))
+        .unwrap();
+    cpu.enable_step_history(4);
+
+    cpu.step().unwrap();
+    cpu.step_instruction().unwrap();
+    let before_last = cpu.state();
+    cpu.step_instruction().unwrap();
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x03);
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.state(), before_last);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x02);
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.pc(), 0x0001);
+    assert_eq!(cpu.registers().fetch(register!(A)), 0x01);
+}
+
+#[test]
+fn joypad_select_survives_round_trip() {
+    let mut cpu = running_cpu();
+    // Select the action buttons only
+    cpu.memory_mut().write_byte(P1, 0x10);
+    let saved = cpu.save_state();
+
+    cpu.memory_mut().write_byte(P1, 0x20);
+    cpu.load_state(&saved).unwrap();
+    assert_eq!(cpu.memory().read_byte(P1), 0xDF);
+
+    // Only the restored group reaches P1
+    cpu.set_button(Button::Up, true);
+    assert_eq!(cpu.memory().read_byte(P1), 0xDF);
+    cpu.set_button(Button::Start, true);
+    assert_eq!(cpu.memory().read_byte(P1), 0xD7);
+}
@@ -0,0 +1,77 @@
+use gaemboi::cpu::interrupts::INTERRUPT_FLAG;
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::{Address, Memory};
+use gaemboi::timer::{DIV, TAC, TIMA, TMA};
+
+#[test]
+fn fastest_rate_overflow_raises_interrupt() {
+    let mut memory = Memory::default();
+    // Enabled, 16 clocks (4 machine cycles) per increment
+    memory.write_byte(TAC, 0b101);
+    memory.write_byte(TMA, 0xF0);
+    memory.write_byte(TIMA, 0xFE);
+
+    memory.tick(4);
+    assert_eq!(memory.read_byte(TIMA), 0xFF);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 0x00);
+
+    memory.tick(3);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 0x00);
+
+    memory.tick(1);
+    assert_eq!(memory.read_byte(TIMA), 0xF0);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 1 << 2);
+}
+
+#[test]
+fn div_counts_at_16384_hz_and_resets_on_write() {
+    let mut memory = Memory::default();
+
+    // 256 clocks, 64 machine cycles, per increment
+    memory.tick(63);
+    assert_eq!(memory.read_byte(DIV), 0);
+    memory.tick(1);
+    assert_eq!(memory.read_byte(DIV), 1);
+
+    memory.tick(64 * 10);
+    assert_eq!(memory.read_byte(DIV), 11);
+
+    memory.write_byte(DIV, 0x5A);
+    assert_eq!(memory.read_byte(DIV), 0);
+}
+
+#[test]
+fn disabled_timer_leaves_tima() {
+    let mut memory = Memory::default();
+    memory.write_byte(TAC, 0b001);
+    memory.write_byte(TIMA, 0xFF);
+
+    memory.tick(1000);
+
+    assert_eq!(memory.read_byte(TIMA), 0xFF);
+    assert_eq!(memory.read_byte(INTERRUPT_FLAG), 0x00);
+}
+
+#[test]
+fn div_counts_while_halted_and_resets_mid_halt() {
+    let mut cpu = Cpu::default();
+    // HALT, with nothing enabled to wake it
+    cpu.load_rom_with_entry(&[0x76], Address(0x0000)).unwrap();
+    cpu.step().unwrap();
+    assert!(cpu.is_halted());
+
+    // Stepping one cycle at a time and fast-forwarding agree
+    for _ in 0..63 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(cpu.read_div(), 1);
+    cpu.run_for_cycles(64 * 10).unwrap();
+    assert_eq!(cpu.read_div(), 11);
+    assert!(cpu.is_halted());
+
+    cpu.memory_mut().write_byte(DIV, 0x5A);
+    assert_eq!(cpu.read_div(), 0);
+    cpu.run_for_cycles(64).unwrap();
+    assert_eq!(cpu.read_div(), 1);
+    assert!(cpu.is_halted());
+}
@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use gaemboi::cpu::Cpu;
+use gaemboi::memory::Address;
+use gaemboi::register;
+
+/// A writer the test can still read after handing it to the CPU
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn trace_matches_gameboy_doctor_format() {
+    let mut cpu = Cpu::default();
+    // NOP; INC A; JP 0x0213
+    cpu.load_rom_with_entry(&[0x00, 0x3C, 0xC3, 0x13, 0x02], Address(0x0000))
+        .unwrap();
+    cpu.registers_mut().write(register!(AF), 0x01B0);
+    cpu.registers_mut().write(register!(SP), 0xFFFE);
+
+    let buffer = SharedBuffer::default();
+    cpu.enable_trace(Box::new(buffer.clone()));
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    assert_eq!(
+        trace,
+        "A:01 F:B0 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0000 PCMEM:00,3C,C3,13\n\
+         A:01 F:B0 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0001 PCMEM:3C,C3,13,02\n"
+    );
+
+    // Peeking at PCMEM doesn't count as bus traffic
+    assert_eq!(cpu.memory_accesses(), 2);
+
+    cpu.disable_trace();
+    cpu.step().unwrap();
+    assert_eq!(buffer.0.borrow().iter().filter(|&&b| b == b'\n').count(), 2);
+}